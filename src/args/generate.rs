@@ -0,0 +1,494 @@
+//! # Generate Module
+//! Generates the help message, the man page and the shell completions from the `Spec` of a
+//! program, so they can't drift from the options it accepts
+//!
+//! ```rust
+//! use drgrep::args::generate::{help, Program};
+//! use drgrep::args::parser::{ArgSpec, Spec, ValueType};
+//!
+//! static SPEC: Spec = Spec::new(&[
+//!     ArgSpec::flag("verbose").short('v').help("Print more"),
+//!     ArgSpec::value("color", "WHEN")
+//!         .value_type(ValueType::Choice(&["auto", "never"]))
+//!         .default("auto")
+//!         .help("When to color the output"),
+//! ]);
+//! static PROGRAM: Program = Program {
+//!     name: "prog",
+//!     version: "v1.0.0",
+//!     about: "A program",
+//!     usage: &["[OPTIONS] FILE"],
+//!     positionals: &[("FILE", "The file to read")],
+//!     spec: &SPEC,
+//! };
+//!
+//! assert_eq!(
+//!     "\
+//! A program
+//!
+//! Usage:
+//!   prog [OPTIONS] FILE
+//!
+//! Arguments:
+//!   FILE  The file to read
+//!
+//! Options:
+//!   -v, --verbose       Print more
+//!       --color <WHEN>  When to color the output [default: auto] [possible values: auto, never]
+//! ",
+//!     help(&PROGRAM)
+//! );
+//! ```
+
+use std::str::FromStr;
+
+use super::parser::{ArgSpec, Spec, ValueType};
+
+/// ## Program description
+/// What the generated texts say about the program, besides its options
+#[derive(Debug)]
+pub struct Program<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    /// A one line description
+    pub about: &'a str,
+    /// The usage lines, without the program name
+    pub usage: &'a [&'a str],
+    /// The positional arguments and their help, completed as paths
+    pub positionals: &'a [(&'a str, &'a str)],
+    pub spec: &'a Spec,
+}
+
+/// ## Generated files
+/// The man page and the completion scripts of the shells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Man,
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "man" => Ok(Target::Man),
+            "bash" => Ok(Target::Bash),
+            "zsh" => Ok(Target::Zsh),
+            "fish" => Ok(Target::Fish),
+            "powershell" => Ok(Target::PowerShell),
+            _ => Err(format!("unknown generated file '{}'", s)),
+        }
+    }
+}
+
+/// Generates the man page or the completion script
+pub fn generate(program: &Program, target: Target) -> String {
+    match target {
+        Target::Man => man(program),
+        Target::Bash => bash(program),
+        Target::Zsh => zsh(program),
+        Target::Fish => fish(program),
+        Target::PowerShell => powershell(program),
+    }
+}
+
+/// The option as written in the help: `-k, --key <KEY>`
+fn label(option: &ArgSpec) -> String {
+    let short = match option.short {
+        Some(c) => format!("-{}, ", c),
+        None => "    ".to_string(),
+    };
+    let value = match (option.takes_value, option.optional_value) {
+        (false, _) => String::new(),
+        (true, false) => format!(" <{}>", option.value_name),
+        (true, true) => format!(" [{}]", option.value_name),
+    };
+    format!("{}--{}{}", short, option.name, value)
+}
+
+/// The help of the option followed by its default, its choices and its env variable
+fn description(option: &ArgSpec) -> String {
+    let mut description = option.help.to_string();
+    if let Some(default) = option.default {
+        description.push_str(&format!(" [default: {}]", default));
+    }
+    if let ValueType::Choice(choices) = option.value_type {
+        description.push_str(&format!(" [possible values: {}]", choices.join(", ")));
+    }
+    if let Some(env) = option.env {
+        description.push_str(&format!(" [env: {}]", env));
+    }
+    description
+}
+
+/// The choices completing the value of the option, empty for a free value
+fn choices(option: &ArgSpec) -> &'static [&'static str] {
+    match option.value_type {
+        ValueType::Choice(choices) => choices,
+        _ => &[],
+    }
+}
+
+/// Every alias of the options: `-k`, `--key`...
+fn aliases(spec: &Spec) -> Vec<String> {
+    spec.options()
+        .iter()
+        .flat_map(|o| {
+            o.short
+                .map(|c| format!("-{}", c))
+                .into_iter()
+                .chain(std::iter::once(format!("--{}", o.name)))
+        })
+        .collect()
+}
+
+/// Generates the `--help` message
+pub fn help(program: &Program) -> String {
+    let mut help = format!("{}\n\nUsage:\n", program.about);
+    for usage in program.usage {
+        help.push_str(&format!("  {} {}\n", program.name, usage));
+    }
+    if !program.positionals.is_empty() {
+        help.push_str("\nArguments:\n");
+        let width = program.positionals.iter().map(|(n, _)| n.len()).max();
+        let width = width.unwrap_or_default();
+        for (name, text) in program.positionals {
+            help.push_str(&format!("  {:width$}  {}\n", name, text, width = width));
+        }
+    }
+    help.push_str("\nOptions:\n");
+    let labels: Vec<String> = program.spec.options().iter().map(label).collect();
+    let width = labels.iter().map(String::len).max().unwrap_or_default();
+    for (label, option) in labels.iter().zip(program.spec.options()) {
+        help.push_str(&format!(
+            "  {:width$}  {}\n",
+            label,
+            description(option),
+            width = width
+        ));
+    }
+    help
+}
+
+/// Escapes a text for roff: the backslashes, the dashes and the leading control characters
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Generates the roff man page, for `man -l drgrep.1`
+fn man(program: &Program) -> String {
+    let mut page = format!(
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"\n",
+        program.name.to_uppercase(),
+        program.name,
+        program.version
+    );
+    page.push_str(&format!(
+        ".SH NAME\n{} \\- {}\n.SH SYNOPSIS\n",
+        program.name,
+        roff(program.about)
+    ));
+    for (i, usage) in program.usage.iter().enumerate() {
+        if i > 0 {
+            page.push_str(".br\n");
+        }
+        page.push_str(&format!(".B {}\n{}\n", program.name, roff(usage)));
+    }
+    if !program.positionals.is_empty() {
+        page.push_str(".SH ARGUMENTS\n");
+        for (name, text) in program.positionals {
+            page.push_str(&format!(".TP\n\\fI{}\\fR\n{}\n", name, roff(text)));
+        }
+    }
+    page.push_str(".SH OPTIONS\n");
+    for option in program.spec.options() {
+        let mut names = String::new();
+        if let Some(c) = option.short {
+            names.push_str(&format!("\\fB\\-{}\\fR, ", c));
+        }
+        names.push_str(&format!("\\fB\\-\\-{}\\fR", roff(option.name)));
+        if option.takes_value {
+            names.push_str(&format!(" \\fI{}\\fR", option.value_name));
+        }
+        page.push_str(&format!(".TP\n{}\n{}\n", names, roff(&description(option))));
+    }
+    let variables: Vec<&ArgSpec> = program
+        .spec
+        .options()
+        .iter()
+        .filter(|o| o.env.is_some())
+        .collect();
+    if !variables.is_empty() {
+        page.push_str(".SH ENVIRONMENT\n");
+        for option in variables {
+            page.push_str(&format!(
+                ".TP\n\\fB{}\\fR\nUsed when \\fB\\-\\-{}\\fR is missing.\n",
+                option.env.unwrap_or_default(),
+                roff(option.name)
+            ));
+        }
+    }
+    page.push_str(&format!(".SH VERSION\n{}\n", roff(program.version)));
+    page
+}
+
+/// The options written `-k|--key` in a shell `case`
+fn case_pattern(option: &ArgSpec) -> String {
+    match option.short {
+        Some(c) => format!("-{}|--{}", c, option.name),
+        None => format!("--{}", option.name),
+    }
+}
+
+fn bash(program: &Program) -> String {
+    let function = format!("_{}", program.name.replace('-', "_"));
+    let mut script = format!(
+        "{}() {{\n    local cur prev\n    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    \
+         prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n\n    case \"$prev\" in\n",
+        function
+    );
+    for option in program.spec.options().iter().filter(|o| o.takes_value) {
+        let action = match option.value_type {
+            ValueType::Path => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+            ValueType::Choice(choices) => format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                choices.join(" ")
+            ),
+            // A free value, nothing to complete
+            _ => "COMPREPLY=()".to_string(),
+        };
+        script.push_str(&format!(
+            "        {})\n            {}\n            return\n            ;;\n",
+            case_pattern(option),
+            action
+        ));
+    }
+    script.push_str(&format!(
+        "    esac\n\n    if [[ \"$cur\" == -* ]]; then\n        \
+         COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n    else\n        \
+         COMPREPLY=($(compgen -f -- \"$cur\"))\n    fi\n}}\n\ncomplete -o filenames -F {} {}\n",
+        aliases(program.spec).join(" "),
+        function,
+        program.name
+    ));
+    script
+}
+
+/// Escapes a description for a zsh `_arguments` spec between single quotes
+fn zsh_description(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh(program: &Program) -> String {
+    let function = format!("_{}", program.name.replace('-', "_"));
+    let mut script = format!(
+        "#compdef {}\n\n{}() {{\n    _arguments -s \\\n",
+        program.name, function
+    );
+    for option in program.spec.options() {
+        let description = zsh_description(&description(option));
+        let names = match option.short {
+            Some(c) if option.repeatable => format!("'*'{{-{},--{}}}'", c, option.name),
+            Some(c) => format!("'(-{} --{})'{{-{},--{}}}'", c, option.name, c, option.name),
+            None if option.repeatable => format!("'*--{}", option.name),
+            None => format!("'--{}", option.name),
+        };
+        let value = if !option.takes_value {
+            String::new()
+        } else {
+            let action = match option.value_type {
+                ValueType::Path => "_files".to_string(),
+                ValueType::Choice(choices) => format!("({})", choices.join(" ")),
+                _ => " ".to_string(),
+            };
+            let optional = if option.optional_value { ":" } else { "" };
+            format!(":{}{}:{}", optional, option.value_name, action)
+        };
+        script.push_str(&format!(
+            "        {}[{}]{}' \\\n",
+            names, description, value
+        ));
+    }
+    script.push_str(&format!(
+        "        '*:argument:_files'\n}}\n\n{} \"$@\"\n",
+        function
+    ));
+    script
+}
+
+/// Quotes a text between single quotes for fish
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish(program: &Program) -> String {
+    let mut script = String::new();
+    for option in program.spec.options() {
+        let mut line = format!("complete -c {}", program.name);
+        if let Some(c) = option.short {
+            line.push_str(&format!(" -s {}", c));
+        }
+        line.push_str(&format!(" -l {}", option.name));
+        if option.takes_value {
+            line.push_str(match (option.value_type, option.optional_value) {
+                (ValueType::Path, _) => " -r -F",
+                (_, false) => " -x",
+                (_, true) => " -f",
+            });
+            let choices = choices(option);
+            if !choices.is_empty() {
+                line.push_str(&format!(" -a {}", fish_quote(&choices.join(" "))));
+            }
+        }
+        line.push_str(&format!(" -d {}\n", fish_quote(option.help)));
+        script.push_str(&line);
+    }
+    script
+}
+
+/// Quotes a text between single quotes for PowerShell
+fn powershell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+fn powershell(program: &Program) -> String {
+    let mut script = format!(
+        "Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{\n    \
+         param($wordToComplete, $commandAst, $cursorPosition)\n\n    \
+         $words = @($commandAst.CommandElements |\n        \
+         Where-Object {{ $_.Extent.EndOffset -lt $cursorPosition }} |\n        \
+         ForEach-Object {{ $_.ToString() }})\n    \
+         $previous = if ($words.Count -gt 1) {{ $words[-1] }} else {{ '' }}\n\n    \
+         $values = switch ($previous) {{\n",
+        powershell_quote(program.name)
+    );
+    for option in program.spec.options() {
+        let choices = choices(option);
+        if choices.is_empty() {
+            continue;
+        }
+        let names: Vec<String> = case_pattern(option)
+            .split('|')
+            .map(powershell_quote)
+            .collect();
+        let values: Vec<String> = choices.iter().map(|c| powershell_quote(c)).collect();
+        script.push_str(&format!(
+            "        {{ $_ -cin @({}) }} {{ @({}) }}\n",
+            names.join(", "),
+            values.join(", ")
+        ));
+    }
+    script.push_str(
+        "    }\n    if ($null -ne $values) {\n        \
+         $values | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n            \
+         [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n        \
+         }\n        return\n    }\n\n    \
+         # The paths are completed by PowerShell when nothing is returned\n    \
+         if ($wordToComplete -like '-*') {\n        $options = @(\n",
+    );
+    for option in program.spec.options() {
+        for alias in case_pattern(option).split('|') {
+            script.push_str(&format!(
+                "            @({}, {})\n",
+                powershell_quote(alias),
+                powershell_quote(option.help)
+            ));
+        }
+    }
+    script.push_str(
+        "        )\n        $options | Where-Object { $_[0] -clike \"$wordToComplete*\" } | \
+         ForEach-Object {\n            \
+         [System.Management.Automation.CompletionResult]::new($_[0], $_[0], 'ParameterName', $_[1])\n        \
+         }\n    }\n}\n",
+    );
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SPEC: Spec = Spec::new(&[
+        ArgSpec::flag("verbose").short('v').help("Print more"),
+        ArgSpec::value("file", "FILE")
+            .short('f')
+            .repeatable()
+            .value_type(ValueType::Path)
+            .help("A file: read it"),
+        ArgSpec::value("color", "WHEN")
+            .value_type(ValueType::Choice(&["auto", "never"]))
+            .env("PROG_COLOR")
+            .help("When to color the output"),
+        ArgSpec::value("undo", "ID").optional().help("Undo a run"),
+    ]);
+    static PROGRAM: Program = Program {
+        name: "prog",
+        version: "v1.0.0",
+        about: "A program",
+        usage: &["[OPTIONS] PATTERN [PATH...]"],
+        positionals: &[("PATTERN", "The regex"), ("PATH", "A file or a directory")],
+        spec: &SPEC,
+    };
+
+    #[test]
+    fn test_help() {
+        let help = help(&PROGRAM);
+        assert!(help.contains("  -f, --file <FILE>   A file: read it\n"));
+        assert!(help.contains("      --undo [ID]     Undo a run\n"));
+        assert!(help
+            .contains("When to color the output [possible values: auto, never] [env: PROG_COLOR]"));
+        assert!(help.contains("  PATTERN  The regex\n"));
+    }
+
+    #[test]
+    fn test_man() {
+        let page = generate(&PROGRAM, Target::Man);
+        assert!(page.starts_with(".TH PROG 1 \"\" \"prog v1.0.0\" \"User Commands\"\n"));
+        assert!(page.contains(".TP\n\\fB\\-f\\fR, \\fB\\-\\-file\\fR \\fIFILE\\fR\n"));
+        assert!(page.contains(".SH ENVIRONMENT\n.TP\n\\fBPROG_COLOR\\fR\n"));
+    }
+
+    #[test]
+    fn test_completions() {
+        let bash = generate(&PROGRAM, Target::Bash);
+        assert!(
+            bash.contains("        -f|--file)\n            COMPREPLY=($(compgen -f -- \"$cur\"))")
+        );
+        assert!(bash.contains("compgen -W \"auto never\""));
+        assert!(bash.contains("compgen -W \"-v --verbose -f --file --color --undo\""));
+        assert!(bash.ends_with("complete -o filenames -F _prog prog\n"));
+
+        let zsh = generate(&PROGRAM, Target::Zsh);
+        assert!(zsh.contains("'*'{-f,--file}'[A file\\: read it]:FILE:_files' \\\n"));
+        assert!(zsh.contains("'--color[When to color the output \\[possible values"));
+        assert!(zsh.contains(":WHEN:(auto never)' \\\n"));
+        assert!(zsh.contains("'--undo[Undo a run]::ID: ' \\\n"));
+
+        let fish = generate(&PROGRAM, Target::Fish);
+        assert!(fish.contains("complete -c prog -s f -l file -r -F -d 'A file: read it'\n"));
+        assert!(fish.contains("complete -c prog -l color -x -a 'auto never' -d"));
+
+        let powershell = generate(&PROGRAM, Target::PowerShell);
+        assert!(powershell.contains("{ $_ -cin @('--color') } { @('auto', 'never') }"));
+        assert!(powershell.contains("@('-f', 'A file: read it')"));
+    }
+
+    #[test]
+    fn test_target_names() {
+        assert_eq!(Ok(Target::PowerShell), "powershell".parse());
+        assert!("tcsh".parse::<Target>().is_err());
+    }
+}
@@ -5,7 +5,8 @@
 //! ## Features
 //!
 //! - Parses command-line arguments in short (`-a`) and long (`--argument`) format
-//! - Supports arguments with or without values
+//! - Supports arguments with or without values, `--key=value` and grouped short flags (`-abc`)
+//! - Collects the positional arguments, `--` ends the options
 //! - Simple and intuitive interface
 //! - No external dependencies
 //!
@@ -46,6 +47,33 @@
 //! }
 //! ```
 //!
+//! ## Declaring the options
+//!
+//! A `Spec` lists the options of the program: their short and long aliases, their value, its
+//! type and default, an env variable fallback and whether they can be repeated. It validates the
+//! parsed arguments and reads them by long name, whatever the alias used:
+//!
+//! ```rust
+//! use drgrep::args::parser::{ArgParser, ArgSpec, Spec, ValueType};
+//!
+//! static SPEC: Spec = Spec::new(&[
+//!     ArgSpec::flag("verbose").short('v').env("MY_PROGRAM_VERBOSE"),
+//!     ArgSpec::value("file", "FILE").short('f').repeatable(),
+//!     ArgSpec::value("jobs", "N").value_type(ValueType::Number).default("1"),
+//! ]);
+//!
+//! fn your_fn() -> Result<(), drgrep::args::parser::ArgError> {
+//!     // `-f -file.txt` reads `-file.txt` as the value of `-f`
+//!     let parser = ArgParser::with_spec(&SPEC);
+//!     // Fails on the unknown options and the invalid values
+//!     let args = SPEC.args(&parser)?;
+//!     let verbose = args.get_bool("verbose");
+//!     let files = args.get_many("file");
+//!     let jobs = args.get_usize("jobs");
+//!     Ok(())
+//! }
+//! ```
+//!
 //! ## Command Line Examples
 //!
 //! The parser supports the following argument formats:
@@ -61,14 +89,21 @@
 //!
 //! # Combination of arguments
 //! $ ./my_program --verbose --file test.txt -o output.log
+//!
+//! # Values attached to the option, grouped short flags
+//! $ ./my_program --file=test.txt -vftest.txt
+//!
+//! # Values starting with `-`, read as values when the spec says the option takes one
+//! $ ./my_program --regex '-\d+'
+//!
+//! # Positional arguments, everything after `--` is one
+//! $ ./my_program --verbose input.txt -- -not-an-option.txt
 //! ```
 //!
 //! ## Current Limitations
 //!
-//! - No support for positional arguments (not preceded by `-` or `--`)
-//! - No support for grouped arguments (like `-abc` for `-a -b -c`)
-//! - No support for arguments with values in the form `--key=value`
-//! - No built-in validation for required arguments
+//! - Without a `Spec`, a value can't start with `-` and only the last flag of a group takes one
+//! - No support for the abbreviations of the long options (like `--verb` for `--verbose`)
 //!
 //! ## Contributing
 //!
@@ -78,4 +113,5 @@
 //!
 //! This tool is distributed under the [MIT License].
 
-pub mod parser;
\ No newline at end of file
+pub mod generate;
+pub mod parser;
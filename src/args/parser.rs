@@ -1,7 +1,7 @@
 //! # Parser Module
 //! Provide the basic helper for Command line argument parsing
 
-use std::{collections::HashMap, env};
+use std::{collections::HashMap, env, error::Error, fmt};
 
 
 /// ## Argument parser
@@ -10,45 +10,156 @@ use std::{collections::HashMap, env};
 /// The constructor retrieve automatically the args inside the `std::env` and put it in a `HasMap`
 /// 
 /// Use Helper to interact safely the provided args
+/// 
+/// An argument given several times keeps its last value in `args`, every value is kept in `values`
 #[derive(Debug)]
 pub struct ArgParser {
     pub args: HashMap<String, Option<String>>,
+    pub values: HashMap<String, Vec<String>>,
+    /// The arguments that aren't options, in the command line order
+    pub positionals: Vec<String>,
+    /// The rank of each value of `values` among the values and the positionals, by key, to
+    /// merge several of them in the command line order
+    pub ranks: HashMap<String, Vec<usize>>,
+    /// The rank of each positional, see `ranks`
+    pub positional_ranks: Vec<usize>,
 }
 
 
 impl ArgParser {
     /// Create a new instance of `ArgParser`
+    ///
+    /// Without a spec, an option takes the next argument as its value unless it looks like an
+    /// option, see `ArgParser::with_spec`
     pub fn new() -> Self {
-        let mut args = HashMap::new();
-        let mut iter = env::args().skip(1).peekable();
+        Self::parse(env::args().skip(1), None)
+    }
+
+    /// Parses the command line, the options of the spec taking a value read the next argument
+    /// even when it starts with `-`, like `-k -foo`
+    pub fn with_spec(spec: &Spec) -> Self {
+        Self::parse(env::args().skip(1), Some(spec))
+    }
+
+    /// Parses the arguments, the program name excluded
+    ///
+    /// - `--key value` and `--key=value`
+    /// - `-k value`, `-kvalue` and the grouped short flags `-sv` for `-s -v`
+    /// - `--` ends the options, the arguments after it are positionals
+    /// - `-` alone is a positional, for the standard input
+    ///
+    /// ```rust
+    /// use drgrep::args::parser::ArgParser;
+    ///
+    /// let parser = ArgParser::parse(["--path=src", "-sv", "--", "-k"], None);
+    /// assert_eq!(&Some("src".to_string()), parser.get("path"));
+    /// assert!(parser.has("s") && parser.has("v"));
+    /// assert_eq!(["-k"], parser.positionals.as_slice());
+    /// ```
+    pub fn parse<I>(args: I, spec: Option<&Spec>) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut parser = Self {
+            args: HashMap::new(),
+            values: HashMap::new(),
+            positionals: Vec::new(),
+            ranks: HashMap::new(),
+            positional_ranks: Vec::new(),
+        };
+        let mut iter = args.into_iter().map(Into::into).peekable();
+        // Whether the option takes a value, whether it can be left out and whether the spec
+        // knows it
+        let arity = |key: &str| match spec.and_then(|s| s.find(key)) {
+            Some(option) => (option.takes_value, option.optional_value, true),
+            // Unknown to the spec, the value is guessed like without spec
+            None => (true, true, false),
+        };
+        let looks_like_option = |arg: &String| arg.starts_with('-') && arg.len() > 1;
 
         while let Some(arg) = iter.next() {
-            if arg.starts_with("--") {
-                let key = arg.trim_start_matches("--").to_string();
-                if let Some(value) = iter.peek() {
-                    if !value.starts_with("--") {
-                        args.insert(key, Some(iter.next().unwrap()));
-                    } else {
-                        args.insert(key, None);
-                    }
-                } else {
-                    args.insert(key, None);
+            if arg == "--" {
+                for arg in iter.by_ref() {
+                    parser.push_positional(arg);
+                }
+            } else if let Some(long) = arg.strip_prefix("--") {
+                if let Some((key, value)) = long.split_once('=') {
+                    parser.insert(key, Some(value.to_string()));
+                    continue;
                 }
-            } else if arg.starts_with("-") {
-                let key = arg.trim_start_matches("-").to_string();
-                if let Some(value) = iter.peek() {
-                    if !value.starts_with("-") {
-                        args.insert(key, Some(iter.next().unwrap()));
-                    } else {
-                        args.insert(key, None);
+                let value = match arity(long) {
+                    (true, false, _) => iter.next(),
+                    (true, true, _) => iter.next_if(|v| !looks_like_option(v)),
+                    (false, _, _) => None,
+                };
+                parser.insert(long, value);
+            } else if looks_like_option(&arg) {
+                let shorts = &arg[1..];
+                for (i, c) in shorts.char_indices() {
+                    let key = c.to_string();
+                    let rest = &shorts[i + c.len_utf8()..];
+                    let (takes_value, optional, known) = arity(&key);
+                    // Unknown to the spec, only the last flag of a group takes a value
+                    if takes_value && (known || rest.is_empty()) {
+                        let value = if !rest.is_empty() {
+                            Some(rest.to_string())
+                        } else if optional {
+                            iter.next_if(|v| !looks_like_option(v))
+                        } else {
+                            iter.next()
+                        };
+                        parser.insert(&key, value);
+                        break;
                     }
-                } else {
-                    args.insert(key, None);
+                    parser.insert(&key, None);
                 }
+            } else {
+                parser.push_positional(arg);
             }
         }
 
-        Self { args }
+        parser
+    }
+
+    /// Records an argument, its value kept after the previous ones
+    pub(crate) fn insert(&mut self, key: &str, value: Option<String>) {
+        if let Some(value) = &value {
+            let rank = self.next_rank();
+            self.ranks.entry(key.to_string()).or_default().push(rank);
+            self.values
+                .entry(key.to_string())
+                .or_default()
+                .push(value.clone());
+        }
+        self.args.insert(key.to_string(), value);
+    }
+
+    /// Records a positional after the previous arguments
+    fn push_positional(&mut self, arg: String) {
+        let rank = self.next_rank();
+        self.positional_ranks.push(rank);
+        self.positionals.push(arg);
+    }
+
+    /// The rank of the next value or positional, after all the recorded ones
+    fn next_rank(&self) -> usize {
+        self.ranks
+            .values()
+            .flatten()
+            .chain(&self.positional_ranks)
+            .max()
+            .map_or(0, |rank| rank + 1)
+    }
+
+    /// Pairs the values with their ranks, the ones without rank coming last
+    fn ranked<'a>(values: &'a [String], ranks: Option<&[usize]>) -> Vec<(usize, &'a str)> {
+        let ranks = ranks.unwrap_or_default();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (ranks.get(i).copied().unwrap_or(usize::MAX), value.as_str()))
+            .collect()
     }
 
     pub fn get(&self, key: &str) -> &Option<String> {
@@ -58,15 +169,54 @@ impl ArgParser {
         }
     }
 
+    /// Returns every value given to the argument, in the command line order
+    pub fn get_all(&self, key: &str) -> &[String] {
+        match self.values.get(key) {
+            Some(v) => v,
+            None => &[],
+        }
+    }
+
+    /// Returns every value given to the argument with its rank, see `ArgParser::ranks`
+    pub fn get_all_ranked(&self, key: &str) -> Vec<(usize, &str)> {
+        Self::ranked(self.get_all(key), self.ranks.get(key).map(Vec::as_slice))
+    }
+
+    /// Returns the positionals with their ranks, see `ArgParser::ranks`
+    pub fn positionals_ranked(&self) -> Vec<(usize, &str)> {
+        Self::ranked(&self.positionals, Some(&self.positional_ranks))
+    }
+
     pub fn has(&self, key: &str) -> bool {
         self.args.contains_key(key)
     }
 
     pub fn set(&mut self, key: &str, val: String) {
+        let rank = self.next_rank();
+        self.ranks.insert(key.to_string(), vec![rank]);
+        self.values.insert(key.to_string(), vec![val.clone()]);
         self.args.insert(key.to_string(), Some(val));
     }
 }
 
+impl From<HashMap<String, Option<String>>> for ArgParser {
+    /// Creates a parser from already parsed arguments, each one having a single value
+    fn from(args: HashMap<String, Option<String>>) -> Self {
+        let values: HashMap<String, Vec<String>> = args
+            .iter()
+            .filter_map(|(k, v)| v.as_ref().map(|v| (k.clone(), vec![v.clone()])))
+            .collect();
+        let ranks = values.keys().enumerate().map(|(i, k)| (k.clone(), vec![i])).collect();
+        Self {
+            args,
+            values,
+            positionals: Vec::new(),
+            ranks,
+            positional_ranks: Vec::new(),
+        }
+    }
+}
+
 impl Default for ArgParser  {
     fn default() -> Self {
         Self::new()
@@ -74,6 +224,469 @@ impl Default for ArgParser  {
 }
 
 
+/// ## Value types
+/// The type of the value taken by an option, checked by `Spec::validate`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    /// Any text
+    Text,
+    /// A non-negative integer
+    Number,
+    /// A file or a directory, completed as such by the shells
+    Path,
+    /// One of the listed names
+    Choice(&'static [&'static str]),
+}
+
+/// ## Option definition
+/// A named option of the command line, with its aliases and its value
+///
+/// ```rust
+/// use drgrep::args::parser::{ArgSpec, ValueType};
+///
+/// let fuzzy = ArgSpec::value("fuzzy", "N").value_type(ValueType::Number);
+/// let sensitive = ArgSpec::flag("sensitive").short('s').env("DRGREP_SENSITIVE_CASE");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArgSpec {
+    /// The long name, used as `--name` and to read the option
+    pub name: &'static str,
+    /// The short alias, used as `-c`
+    pub short: Option<char>,
+    /// Whether the option takes a value
+    pub takes_value: bool,
+    /// Whether the value can be left out, like `--undo [ID]`
+    pub optional_value: bool,
+    /// The name of the value in the messages, like `<N>`
+    pub value_name: &'static str,
+    pub value_type: ValueType,
+    /// The value used when the option is missing
+    pub default: Option<&'static str>,
+    /// The env variable read when the option is missing, for the flags and the typed values
+    pub env: Option<&'static str>,
+    /// Whether the option can be given several times
+    pub repeatable: bool,
+    pub required: bool,
+    pub help: &'static str,
+}
+
+impl ArgSpec {
+    /// Creates an option without value
+    pub const fn flag(name: &'static str) -> Self {
+        ArgSpec {
+            name,
+            short: None,
+            takes_value: false,
+            optional_value: false,
+            value_name: "",
+            value_type: ValueType::Text,
+            default: None,
+            env: None,
+            repeatable: false,
+            required: false,
+            help: "",
+        }
+    }
+
+    /// Creates an option taking a value
+    pub const fn value(name: &'static str, value_name: &'static str) -> Self {
+        ArgSpec {
+            takes_value: true,
+            value_name,
+            ..ArgSpec::flag(name)
+        }
+    }
+
+    pub const fn short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    /// Lets the value be left out
+    pub const fn optional(mut self) -> Self {
+        self.optional_value = true;
+        self
+    }
+
+    pub const fn value_type(mut self, value_type: ValueType) -> Self {
+        self.value_type = value_type;
+        self
+    }
+
+    pub const fn default(mut self, default: &'static str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub const fn env(mut self, env: &'static str) -> Self {
+        self.env = Some(env);
+        self
+    }
+
+    pub const fn repeatable(mut self) -> Self {
+        self.repeatable = true;
+        self
+    }
+
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub const fn help(mut self, help: &'static str) -> Self {
+        self.help = help;
+        self
+    }
+
+    /// The keys of the option in an `ArgParser`, the long name first
+    fn keys(&self) -> impl Iterator<Item = String> {
+        let name = self.name.to_string();
+        std::iter::once(name).chain(self.short.map(String::from))
+    }
+
+    /// Checks a value against the type of the option
+    fn check(&self, value: &str) -> Result<(), ArgError> {
+        let valid = match self.value_type {
+            ValueType::Text | ValueType::Path => true,
+            ValueType::Number => value.parse::<usize>().is_ok(),
+            ValueType::Choice(choices) => choices.contains(&value),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(ArgError::InvalidValue {
+                option: format!("--{}", self.name),
+                value: value.to_string(),
+                expected: match self.value_type {
+                    ValueType::Choice(choices) => format!("one of {}", choices.join(", ")),
+                    _ => "a number".to_string(),
+                },
+            })
+        }
+    }
+}
+
+/// ## Argument errors
+/// The errors found by `Spec::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgError {
+    /// An option missing from the spec, as written: `--colour` or `-q`, with the closest
+    /// known option
+    Unknown {
+        option: String,
+        suggestion: Option<String>,
+    },
+    /// An option given without its value
+    MissingValue { option: String, value_name: String },
+    /// A flag given a value other than `true` or `false`
+    UnexpectedValue { option: String, value: String },
+    /// An option given several times while it isn't repeatable
+    Repeated(String),
+    /// A value not matching the type of the option
+    InvalidValue {
+        option: String,
+        value: String,
+        expected: String,
+    },
+    /// A required option that isn't given
+    Missing(String),
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgError::Unknown {
+                option,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "unknown option {}, did you mean {}?",
+                option, suggestion
+            ),
+            ArgError::Unknown { option, .. } => write!(f, "unknown option {}", option),
+            ArgError::MissingValue { option, value_name } => {
+                write!(
+                    f,
+                    "{} requires a value, like {} <{}>",
+                    option, option, value_name
+                )
+            }
+            ArgError::UnexpectedValue { option, value } => {
+                write!(f, "{} doesn't take a value, got '{}'", option, value)
+            }
+            ArgError::Repeated(option) => write!(f, "{} can only be given once", option),
+            ArgError::InvalidValue {
+                option,
+                value,
+                expected,
+            } => write!(f, "{} expects {}, got '{}'", option, expected, value),
+            ArgError::Missing(option) => write!(f, "{} is required", option),
+        }
+    }
+}
+
+impl Error for ArgError {}
+
+/// ## Argument spec
+/// The options accepted by a program, declared once as a `static`
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use drgrep::args::parser::{ArgParser, ArgSpec, Spec, ValueType};
+///
+/// static SPEC: Spec = Spec::new(&[
+///     ArgSpec::flag("verbose").short('v'),
+///     ArgSpec::value("jobs", "N").short('j').value_type(ValueType::Number).default("1"),
+/// ]);
+///
+/// let parser = ArgParser::from(HashMap::from([("v".to_string(), None)]));
+/// let args = SPEC.args(&parser).unwrap();
+/// assert!(args.get_bool("verbose"));
+/// assert_eq!(Some(1), args.get_usize("jobs"));
+/// ```
+#[derive(Debug)]
+pub struct Spec {
+    options: &'static [ArgSpec],
+}
+
+impl Spec {
+    pub const fn new(options: &'static [ArgSpec]) -> Self {
+        Spec { options }
+    }
+
+    /// Returns the options, in the declaration order
+    pub fn options(&self) -> &'static [ArgSpec] {
+        self.options
+    }
+
+    /// Finds an option by its long name or its short alias
+    pub fn find(&self, key: &str) -> Option<&'static ArgSpec> {
+        let mut chars = key.chars();
+        let short = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        };
+        self.options
+            .iter()
+            .find(|o| o.name == key || (short.is_some() && o.short == short))
+    }
+
+    /// Returns the long option closest to the mistyped one, within a few edits
+    pub fn suggest(&self, key: &str) -> Option<&'static ArgSpec> {
+        self.options
+            .iter()
+            .map(|o| (edit_distance(key, o.name), o))
+            .filter(|(distance, o)| *distance <= 2 && *distance < o.name.len())
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, o)| o)
+    }
+
+    /// Checks the parsed arguments: unknown options, missing or invalid values, repetitions
+    /// and required options
+    pub fn validate(&self, parser: &ArgParser) -> Result<(), ArgError> {
+        self.check(parser, false)
+    }
+
+    /// Checks the parsed arguments like `Spec::validate`, ignoring the unknown options
+    pub fn validate_lenient(&self, parser: &ArgParser) -> Result<(), ArgError> {
+        self.check(parser, true)
+    }
+
+    fn check(&self, parser: &ArgParser, lenient: bool) -> Result<(), ArgError> {
+        let mut keys: Vec<&String> = parser.args.keys().collect();
+        // Reports the same error whatever the order of the map
+        keys.sort();
+        for key in keys {
+            let option = match self.find(key) {
+                Some(option) => option,
+                None if lenient => continue,
+                // The short options are single letters, too short to guess a typo
+                None if key.chars().count() == 1 => {
+                    return Err(ArgError::Unknown {
+                        option: format!("-{}", key),
+                        suggestion: None,
+                    })
+                }
+                None => {
+                    return Err(ArgError::Unknown {
+                        option: format!("--{}", key),
+                        suggestion: self.suggest(key).map(|o| format!("--{}", o.name)),
+                    })
+                }
+            };
+            match (option.takes_value, parser.get(key)) {
+                (true, None) if !option.optional_value => {
+                    return Err(ArgError::MissingValue {
+                        option: format!("--{}", option.name),
+                        value_name: option.value_name.to_string(),
+                    })
+                }
+                // A flag can be written `--flag true` or `--flag false`
+                (false, Some(value)) if value.parse::<bool>().is_err() => {
+                    return Err(ArgError::UnexpectedValue {
+                        option: format!("--{}", option.name),
+                        value: value.clone(),
+                    })
+                }
+                _ => (),
+            }
+            for value in parser.get_all(key) {
+                option.check(value)?;
+            }
+        }
+        for option in self.options {
+            let given = option.keys().filter(|k| parser.has(k)).count();
+            let values: usize = option.keys().map(|k| parser.get_all(&k).len()).sum();
+            if !option.repeatable && (given > 1 || values > 1) {
+                return Err(ArgError::Repeated(format!("--{}", option.name)));
+            }
+            if option.required && given == 0 {
+                return Err(ArgError::Missing(format!("--{}", option.name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies into `parser` the options of `defaults` it doesn't have under any alias, like
+    /// the options of a config file under the command line, and returns their names
+    pub fn merge(&self, parser: &mut ArgParser, defaults: &ArgParser) -> Vec<&'static str> {
+        let mut merged = Vec::new();
+        for option in self.options {
+            if option.keys().any(|k| parser.has(&k)) {
+                continue;
+            }
+            for key in option.keys().filter(|k| defaults.has(k)) {
+                for value in defaults.get_all(&key) {
+                    parser.insert(&key, Some(value.clone()));
+                }
+                if defaults.get_all(&key).is_empty() {
+                    parser.insert(&key, None);
+                }
+                merged.push(option.name);
+            }
+        }
+        merged
+    }
+
+    /// Validates the parsed arguments and returns their typed view
+    pub fn args<'a>(&'a self, parser: &'a ArgParser) -> Result<Args<'a>, ArgError> {
+        self.validate(parser)?;
+        Ok(Args { spec: self, parser })
+    }
+
+    /// Validates the parsed arguments, ignoring the unknown options, and returns their typed
+    /// view
+    pub fn args_lenient<'a>(&'a self, parser: &'a ArgParser) -> Result<Args<'a>, ArgError> {
+        self.validate_lenient(parser)?;
+        Ok(Args { spec: self, parser })
+    }
+}
+
+/// The Levenshtein distance between two names, in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// ## Typed arguments
+/// The arguments validated against a `Spec`, read by the long name of the options whatever the
+/// alias used on the command line
+///
+/// The getters panic on a name missing from the spec, a programming error.
+#[derive(Debug, Clone, Copy)]
+pub struct Args<'a> {
+    spec: &'a Spec,
+    parser: &'a ArgParser,
+}
+
+impl<'a> Args<'a> {
+    fn option(&self, name: &str) -> &'static ArgSpec {
+        self.spec
+            .options
+            .iter()
+            .find(|o| o.name == name)
+            .unwrap_or_else(|| panic!("--{} is missing from the spec", name))
+    }
+
+    /// The env variable of the option, when it is set
+    fn env(&self, option: &ArgSpec) -> Option<String> {
+        option.env.and_then(|var| env::var(var).ok())
+    }
+
+    /// Returns the underlying parser
+    pub fn parser(&self) -> &'a ArgParser {
+        self.parser
+    }
+
+    /// Whether the option is given on the command line
+    pub fn has(&self, name: &str) -> bool {
+        self.option(name).keys().any(|k| self.parser.has(&k))
+    }
+
+    /// Returns the last value given to the option, or its default
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        let option = self.option(name);
+        option
+            .keys()
+            .find_map(|k| self.parser.get(&k).as_deref())
+            .or(option.default)
+    }
+
+    /// Returns every value given to the option, in the command line order
+    pub fn get_many(&self, name: &str) -> Vec<&'a str> {
+        self.get_many_ranked(name)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// Returns every value given to the option with its rank, in the command line order,
+    /// see `ArgParser::ranks`
+    pub fn get_many_ranked(&self, name: &str) -> Vec<(usize, &'a str)> {
+        let mut values: Vec<(usize, &str)> = self
+            .option(name)
+            .keys()
+            .flat_map(|k| self.parser.get_all_ranked(&k))
+            .collect();
+        // Stable, the values without rank keep their order
+        values.sort_by_key(|(rank, _)| *rank);
+        values
+    }
+
+    /// Whether the flag is given, unless written `--flag false`, or its env variable is set
+    pub fn get_bool(&self, name: &str) -> bool {
+        let option = self.option(name);
+        match option.keys().find(|k| self.parser.has(k)) {
+            Some(key) => self.parser.get(&key).as_deref() != Some("false"),
+            None => self.env(option).is_some(),
+        }
+    }
+
+    /// Returns the number given to the option, read from its env variable or its default
+    /// when it is missing
+    pub fn get_usize(&self, name: &str) -> Option<usize> {
+        let option = self.option(name);
+        match option.keys().find_map(|k| self.parser.get(&k).clone()) {
+            Some(value) => value.parse().ok(),
+            None => self
+                .env(option)
+                .or(option.default.map(str::to_string))
+                .and_then(|v| v.parse().ok()),
+        }
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,7 +702,7 @@ mod tests {
     fn test_has_method() {
         let mut args = HashMap::new();
         args.insert("verbose".to_string(), None);
-        let parser = ArgParser { args };
+        let parser = ArgParser::from(args);
         
         assert!(parser.has("verbose"));
         assert!(!parser.has("nonexistent"));
@@ -100,13 +713,26 @@ mod tests {
         let mut args = HashMap::new();
         args.insert("file".to_string(), Some("test.txt".to_string()));
         args.insert("verbose".to_string(), None);
-        let parser = ArgParser { args };
+        let parser = ArgParser::from(args);
         
         assert_eq!(parser.get("file"), &Some("test.txt".to_string()));
         assert_eq!(parser.get("verbose"), &None);
         assert_eq!(parser.get("nonexistent"), &None);
     }
 
+    #[test]
+    fn test_get_all_method() {
+        let mut parser = ArgParser::from(HashMap::new());
+        assert!(parser.get_all("e").is_empty());
+        parser.values.insert("e".to_string(), vec!["foo".to_string(), "bar".to_string()]);
+        parser.args.insert("e".to_string(), Some("bar".to_string()));
+        assert_eq!(parser.get_all("e"), ["foo", "bar"]);
+        assert_eq!(parser.get("e"), &Some("bar".to_string()));
+
+        parser.set("e", "baz".to_string());
+        assert_eq!(parser.get_all("e"), ["baz"]);
+    }
+
     #[test]
     fn test_default_implementation() {
         let parser = ArgParser::default();
@@ -115,8 +741,202 @@ mod tests {
         assert!(parser.args.is_empty() || !parser.args.is_empty());
     }
 
-    // Note: More comprehensive tests would require either:
-    // 1. Refactoring the code to allow argument injection
-    // 2. Using mock libraries like mockall
-    // 3. Creating integration tests that actually execute the program
+    static SPEC: Spec = Spec::new(&[
+        ArgSpec::flag("verbose").short('v'),
+        ArgSpec::flag("color").env("ARGPARSER_TEST_COLOR"),
+        ArgSpec::value("regex", "PATTERN").short('r').repeatable(),
+        ArgSpec::value("jobs", "N")
+            .short('j')
+            .value_type(ValueType::Number)
+            .default("4"),
+        ArgSpec::value("sort", "ORDER").value_type(ValueType::Choice(&["name", "size"])),
+        ArgSpec::value("undo", "ID").optional(),
+    ]);
+
+    fn parser(args: &[(&str, Option<&str>)]) -> ArgParser {
+        let mut parser = ArgParser::from(HashMap::new());
+        for (key, value) in args {
+            parser
+                .args
+                .insert(key.to_string(), value.map(str::to_string));
+            if let Some(value) = value {
+                parser
+                    .values
+                    .entry(key.to_string())
+                    .or_default()
+                    .push(value.to_string());
+            }
+        }
+        parser
+    }
+
+    #[test]
+    fn test_spec_getters() {
+        let parser = parser(&[
+            ("v", None),
+            ("regex", Some("a")),
+            ("r", Some("b")),
+            ("sort", Some("size")),
+            ("undo", None),
+        ]);
+        let args = SPEC.args(&parser).unwrap();
+        assert!(args.get_bool("verbose"));
+        assert!(!args.get_bool("color"));
+        assert_eq!(vec!["a", "b"], args.get_many("regex"));
+        assert_eq!(Some(4), args.get_usize("jobs"));
+        assert_eq!(Some("size"), args.get("sort"));
+        assert!(args.has("undo"));
+        assert_eq!(None, args.get("undo"));
+
+        let parser = self::parser(&[("j", Some("8")), ("verbose", Some("false"))]);
+        let args = SPEC.args(&parser).unwrap();
+        assert_eq!(Some(8), args.get_usize("jobs"));
+        assert!(!args.get_bool("verbose"));
+    }
+
+    // The env variables are read, never set, so the tests running in parallel can't race
+    static ENV_SPEC: Spec = Spec::new(&[
+        ArgSpec::flag("path").env("PATH"),
+        ArgSpec::flag("unset").env("ARGPARSER_TEST_NEVER_SET"),
+    ]);
+
+    #[test]
+    fn test_spec_env() {
+        let parser = self::parser(&[]);
+        let args = ENV_SPEC.args(&parser).unwrap();
+        assert!(args.get_bool("path"));
+        assert!(!args.get_bool("unset"));
+
+        // The command line wins over the env variable
+        let parser = self::parser(&[("path", Some("false"))]);
+        assert!(!ENV_SPEC.args(&parser).unwrap().get_bool("path"));
+    }
+
+    #[test]
+    fn test_spec_errors() {
+        let error = |args: &[(&str, Option<&str>)]| SPEC.validate(&parser(args)).unwrap_err();
+        assert_eq!(
+            "unknown option --colour, did you mean --color?",
+            error(&[("colour", None)]).to_string()
+        );
+        assert_eq!(
+            ArgError::Unknown {
+                option: "-q".to_string(),
+                suggestion: None
+            },
+            error(&[("q", None)])
+        );
+        assert_eq!(
+            "unknown option --output",
+            error(&[("output", None)]).to_string()
+        );
+        assert!(SPEC
+            .validate_lenient(&parser(&[("colour", Some("always")), ("v", None)]))
+            .is_ok());
+        assert_eq!(
+            "--jobs requires a value, like --jobs <N>",
+            error(&[("j", None)]).to_string()
+        );
+        assert_eq!(
+            "--verbose doesn't take a value, got 'file.txt'",
+            error(&[("verbose", Some("file.txt"))]).to_string()
+        );
+        assert_eq!(
+            ArgError::Repeated("--jobs".to_string()),
+            error(&[("jobs", Some("1")), ("j", Some("2"))])
+        );
+        assert_eq!(
+            "--jobs expects a number, got 'two'",
+            error(&[("jobs", Some("two"))]).to_string()
+        );
+        assert_eq!(
+            "--sort expects one of name, size, got 'date'",
+            error(&[("sort", Some("date"))]).to_string()
+        );
+
+        static REQUIRED: Spec = Spec::new(&[ArgSpec::value("key", "KEY").required()]);
+        assert_eq!(
+            ArgError::Missing("--key".to_string()),
+            REQUIRED.validate(&parser(&[])).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_syntax() {
+        let parser = ArgParser::parse(
+            ["--path=src", "pattern", "-sv", "--key", "a=b", "-", "--", "--regex", "-x"],
+            None,
+        );
+        assert_eq!(&Some("src".to_string()), parser.get("path"));
+        assert!(parser.has("s") && parser.has("v"));
+        assert_eq!(&None, parser.get("s"));
+        assert_eq!(&Some("a=b".to_string()), parser.get("key"));
+        assert!(!parser.has("regex"));
+        assert_eq!(["pattern", "-", "--regex", "-x"], parser.positionals.as_slice());
+        assert_eq!(
+            vec![(1, "pattern"), (3, "-"), (4, "--regex"), (5, "-x")],
+            parser.positionals_ranked()
+        );
+
+        // The values of the long and the short names keep the command line order
+        let parser = ArgParser::parse(["-r", "a", "--regex", "b", "-r", "c"], Some(&SPEC));
+        assert_eq!(vec!["a", "b", "c"], SPEC.args(&parser).unwrap().get_many("regex"));
+
+        // Without spec, a value can't start with `-`
+        let parser = ArgParser::parse(["-k", "-foo", "--only", "-c", "text"], None);
+        assert_eq!(&None, parser.get("k"));
+        assert!(parser.has("f") && parser.has("o"));
+        assert_eq!(&None, parser.get("only"));
+        assert_eq!(&Some("text".to_string()), parser.get("c"));
+    }
+
+    #[test]
+    fn test_parse_with_spec() {
+        let parse = |args: &[&str]| ArgParser::parse(args.iter().copied(), Some(&SPEC));
+        let parser = parse(&["-r", "-\\d+", "--regex", "--", "-vj8", "file"]);
+        assert_eq!(["-\\d+"], parser.get_all("r"));
+        assert_eq!(["--"], parser.get_all("regex"));
+        assert_eq!(&Some("8".to_string()), parser.get("j"));
+        assert!(parser.has("v"));
+        assert_eq!(["file"], parser.positionals.as_slice());
+
+        // The flags don't take the next argument, the optional values stop at an option
+        let parser = parse(&["-v", "file", "--undo", "-v", "--jobs=2"]);
+        assert_eq!(&None, parser.get("v"));
+        assert_eq!(&None, parser.get("undo"));
+        assert_eq!(["file"], parser.positionals.as_slice());
+        let args = SPEC.args(&parser).unwrap();
+        assert_eq!(Some(2), args.get_usize("jobs"));
+
+        // A value taken from the rest of a group
+        let parser = parse(&["-vr-a", "-j", "-1"]);
+        assert_eq!(["-a"], parser.get_all("r"));
+        assert_eq!(&Some("-1".to_string()), parser.get("j"));
+        assert!(SPEC.validate(&parser).is_err());
+
+        // An unknown option guesses its value, for the lenient validation
+        let parser = parse(&["--colour", "always", "-qv", "file"]);
+        assert_eq!(&Some("always".to_string()), parser.get("colour"));
+        assert!(parser.has("q") && parser.has("v"));
+        assert_eq!(["file"], parser.positionals.as_slice());
+        assert!(SPEC.validate_lenient(&parser).is_ok());
+    }
+
+    #[test]
+    fn test_spec_merge() {
+        let mut cli = parser(&[("j", Some("8")), ("r", Some("a"))]);
+        let file = parser(&[
+            ("jobs", Some("2")),
+            ("verbose", None),
+            ("sort", Some("name")),
+        ]);
+        let mut merged = SPEC.merge(&mut cli, &file);
+        merged.sort();
+        assert_eq!(vec!["sort", "verbose"], merged);
+        let args = SPEC.args(&cli).unwrap();
+        assert_eq!(Some(8), args.get_usize("jobs"));
+        assert!(args.get_bool("verbose"));
+        assert_eq!(Some("name"), args.get("sort"));
+        assert_eq!(vec!["a"], args.get_many("regex"));
+    }
 }
\ No newline at end of file
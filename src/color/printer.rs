@@ -120,6 +120,35 @@ pub fn print_partial_colored(parts: TextParts) {
     println!(); // Add newline at the end
 }
 
+/// Prints contiguous text segments with different colors on a single line
+///
+/// Unlike `print_partial_colored` no space is added between the segments, so a line
+/// split at the match boundaries is printed exactly as it is.
+///
+/// # Arguments
+///
+/// * `parts` - A reference to a vector of tuples, each containing text and its color
+///
+/// # Examples
+///
+/// ```
+/// use drgrep::color::config::Color;
+/// use drgrep::color::printer::print_segments;
+///
+/// let parts = vec![
+///     ("let ", Color::WHITE),
+///     ("token", Color::BRIGHT_YELLOW),
+///     (" = 1;", Color::WHITE)
+/// ];
+/// print_segments(&parts);
+/// ```
+pub fn print_segments(parts: TextParts) {
+    for (text, color) in parts {
+        print!("{}{}{}", color, text, Color::RESET);
+    }
+    println!();
+}
+
 /// Macro for printing colored text
 ///
 /// This macro provides a convenient shorthand for calling the `print_colored` function.
@@ -183,6 +212,7 @@ macro_rules! print_partial_colored {
 
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     use super::*;
     use crate::color::config::Color;
@@ -226,6 +256,15 @@ mod tests {
         assert!(true);
     }
 
+    #[test]
+    fn test_print_segments() {
+        let parts = vec![
+            ("Seg", Color::WHITE),
+            ("ment", Color::BRIGHT_YELLOW)
+        ];
+        print_segments(&parts);
+    }
+
     #[test]
     fn test_empty_parts() {
         // Test with empty parts to ensure it doesn't crash
@@ -0,0 +1,474 @@
+//! # Config File Module
+//!
+//! Loads the default options of drgrep from the config files:
+//!
+//! - the project config, the closest `.drgreprc` in the current directory or its parents
+//! - the user config, `$XDG_CONFIG_HOME/drgrep/config.toml`, by default
+//!   `~/.config/drgrep/config.toml`
+//!
+//! Both are TOML files keyed by the long options: `true` or `false` for the flags, a string or
+//! a number for the values, an array for the options that can be repeated. The relative paths
+//! are relative to the directory of the file. The options giving the pattern or the text to
+//! search and the ones rewriting files are only accepted on the command line, a `.drgreprc` in
+//! a parent directory can't turn a search into a rewrite.
+//!
+//! The command line overrides the project config, which overrides the user config, and
+//! `--no-config` ignores them. Setting an option of a group excluding each other, like `-s`,
+//! `-i` and `-S`, overrides the whole group of the lower layers, and the paths given as
+//! positionals override `path`.
+//!
+//! ```rust
+//! use drgrep::config_file::ConfigFile;
+//!
+//! let file = ConfigFile::parse(
+//!     ".drgreprc",
+//!     r#"
+//! smart-case = true
+//! engine = "auto"
+//! path = ["src", "tests"]
+//! "#,
+//! )
+//! .unwrap();
+//! assert!(file.args().has("smart-case"));
+//! assert_eq!(["src", "tests"], file.args().get_all("path"));
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::args::parser::{ArgParser, ValueType};
+use crate::error::DrgrepError;
+use crate::SPEC;
+
+/// The name of the project config file
+pub const PROJECT_FILE: &str = ".drgreprc";
+
+/// The options only accepted on the command line: the pattern, the text to search and the
+/// options rewriting files
+const COMMAND_LINE_ONLY: &[&str] = &[
+    "key",
+    "regex",
+    "regexp",
+    "file",
+    "content",
+    "replace",
+    "in-place",
+    "write",
+    "interactive",
+    "undo",
+];
+
+/// The groups of options excluding each other
+const EXCLUSIVE_GROUPS: &[&[&str]] = &[
+    &["basic-regexp", "extended-regexp", "fixed-strings"],
+    &["sensitive", "ignore-case", "smart-case"],
+    &["word-regexp", "line-regexp"],
+];
+
+/// Where the value of an option comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    CommandLine,
+    File(PathBuf),
+    Env(&'static str),
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::CommandLine => write!(f, "command line"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env(var) => write!(f, "env {}", var),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// The options of a config file
+#[derive(Debug)]
+pub struct ConfigFile {
+    path: PathBuf,
+    args: ArgParser,
+}
+
+impl ConfigFile {
+    /// Parses the content of a config file
+    pub fn parse(path: impl Into<PathBuf>, content: &str) -> Result<Self, DrgrepError> {
+        let path = path.into();
+        let error = |message: String| DrgrepError::ConfigFile {
+            path: path.clone(),
+            message,
+        };
+        let table: toml::Table = content
+            .parse()
+            .map_err(|e: toml::de::Error| error(e.message().to_string()))?;
+        let mut args = ArgParser::from(HashMap::new());
+        for (key, value) in &table {
+            let Some(option) = SPEC.options().iter().find(|o| o.name == *key) else {
+                return Err(error(match SPEC.suggest(key) {
+                    Some(o) => format!("unknown option '{}', did you mean '{}'?", key, o.name),
+                    None => format!("unknown option '{}'", key),
+                }));
+            };
+            if COMMAND_LINE_ONLY.contains(&option.name) {
+                return Err(error(format!(
+                    "'{}' can only be given on the command line",
+                    key
+                )));
+            }
+            let values = match value {
+                toml::Value::Array(values) if option.repeatable => values.iter().collect(),
+                toml::Value::Array(_) => {
+                    return Err(error(format!("'{}' can only be given once", key)))
+                }
+                value => vec![value],
+            };
+            for value in values {
+                let value = match (value, option.takes_value) {
+                    (toml::Value::Boolean(true), false) => None,
+                    (toml::Value::Boolean(false), false) => Some("false".to_string()),
+                    // The paths are relative to the directory of the file, not to the
+                    // current directory
+                    (toml::Value::String(s), true)
+                        if matches!(option.value_type, ValueType::Path) && s != "-" =>
+                    {
+                        let dir = path.parent().unwrap_or(Path::new(""));
+                        Some(dir.join(s).to_string_lossy().into_owned())
+                    }
+                    (toml::Value::String(s), true) => Some(s.clone()),
+                    (toml::Value::Integer(i), true) => Some(i.to_string()),
+                    (_, false) => return Err(error(format!("'{}' expects true or false", key))),
+                    (_, true) => {
+                        return Err(error(format!("'{}' expects a string or a number", key)))
+                    }
+                };
+                args.insert(option.name, value);
+            }
+        }
+        Ok(ConfigFile { path, args })
+    }
+
+    /// Loads a config file, `None` when it doesn't exist
+    pub fn load(path: &Path) -> Result<Option<Self>, DrgrepError> {
+        Self::load_as(path, path)
+    }
+
+    /// Loads a config file under another name, its paths being relative to the directory of
+    /// the name
+    fn load_as(path: &Path, name: &Path) -> Result<Option<Self>, DrgrepError> {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::parse(name, &content).map(Some),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(DrgrepError::io(path, e)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the options of the file, under their long name
+    pub fn args(&self) -> &ArgParser {
+        &self.args
+    }
+}
+
+/// Returns the path of the user config file
+pub fn user_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?)
+            .join(".config"),
+    };
+    Some(config_dir.join("drgrep").join("config.toml"))
+}
+
+/// Returns the closest project config file, in the directory or its parents
+pub fn project_path(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Loads the config files from the most to the least important: the project, then the user
+pub fn load_all(current_dir: &Path) -> Result<Vec<ConfigFile>, DrgrepError> {
+    let mut files = Vec::new();
+    for path in [project_path(current_dir), user_path()]
+        .into_iter()
+        .flatten()
+    {
+        // A config of the current directory keeps its paths relative to it
+        let name = path.strip_prefix(current_dir).unwrap_or(&path);
+        files.extend(ConfigFile::load_as(&path, name)?);
+    }
+    Ok(files)
+}
+
+/// Adds the options of the config files the command line doesn't give, and returns the file
+/// of each one
+pub fn apply(parser: &mut ArgParser, files: &[ConfigFile]) -> HashMap<&'static str, PathBuf> {
+    let mut sources = HashMap::new();
+    for file in files {
+        // The overrides of the upper layers, before merging the file
+        let overridden = overridden(parser);
+        let mut args = ArgParser::from(HashMap::new());
+        for option in SPEC.options() {
+            if overridden.contains(&option.name) || !file.args.has(option.name) {
+                continue;
+            }
+            let values = file.args.get_all(option.name);
+            if values.is_empty() {
+                args.insert(option.name, None);
+            }
+            for value in values {
+                args.insert(option.name, Some(value.clone()));
+            }
+        }
+        for name in SPEC.merge(parser, &args) {
+            sources.insert(name, file.path.clone());
+        }
+    }
+    sources
+}
+
+/// Returns the options of the groups the arguments already set, and `path` when a positional
+/// gives a path
+fn overridden(parser: &ArgParser) -> Vec<&'static str> {
+    let set = |name: &str| {
+        SPEC.find(name).is_some_and(|option| {
+            std::iter::once(option.name.to_string())
+                .chain(option.short.map(String::from))
+                .any(|k| parser.has(&k) && parser.get(&k).as_deref() != Some("false"))
+        })
+    };
+    let mut names: Vec<&'static str> = EXCLUSIVE_GROUPS
+        .iter()
+        .filter(|group| group.iter().any(|name| set(name)))
+        .flat_map(|group| group.iter().copied())
+        .collect();
+    // Like in `Config::new`, the first positional is the pattern unless an option gives one
+    let pattern_positional = !["key", "regex", "regexp", "file"]
+        .iter()
+        .any(|name| set(name));
+    if parser.positionals.len() > usize::from(pattern_positional) {
+        names.push("path");
+    }
+    names
+}
+
+/// Lists the effective options and where their value comes from, for `--debug-config`
+pub fn debug(parser: &ArgParser, files: &HashMap<&'static str, PathBuf>) -> String {
+    let mut lines = String::new();
+    for option in SPEC.options() {
+        let keys: Vec<String> = std::iter::once(option.name.to_string())
+            .chain(option.short.map(String::from))
+            .filter(|k| parser.has(k))
+            .collect();
+        let (value, source) = if !keys.is_empty() {
+            let values: Vec<&str> = keys
+                .iter()
+                .flat_map(|k| parser.get_all(k))
+                .map(String::as_str)
+                .collect();
+            let value = if values.is_empty() {
+                "true".to_string()
+            } else {
+                values.join(", ")
+            };
+            let source = match files.get(option.name) {
+                Some(path) => Source::File(path.clone()),
+                None => Source::CommandLine,
+            };
+            (value, source)
+        } else if let Some(var) = option.env.filter(|var| env::var_os(var).is_some()) {
+            ("true".to_string(), Source::Env(var))
+        } else if let Some(default) = option.default {
+            (default.to_string(), Source::Default)
+        } else {
+            continue;
+        };
+        lines.push_str(&format!("--{} = {} ({})\n", option.name, value, source));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::create_temp_dir;
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |content: &str| {
+            ConfigFile::parse("config.toml", content)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            "config.toml: unknown option 'smart-cas', did you mean 'smart-case'?",
+            error("smart-cas = true")
+        );
+        assert_eq!(
+            "config.toml: 'smart-case' expects true or false",
+            error("smart-case = \"yes\"")
+        );
+        assert_eq!(
+            "config.toml: 'engine' can only be given once",
+            error("engine = [\"auto\", \"fancy\"]")
+        );
+        assert_eq!(
+            "config.toml: 'regexp' can only be given on the command line",
+            error("regexp = [\"a\", \"b\"]")
+        );
+        for content in [
+            "replace = \"PWNED\"",
+            "in-place = true",
+            "write = true",
+            "interactive = false",
+            "undo = \"1\"",
+        ] {
+            let key = content.split(' ').next().unwrap();
+            assert_eq!(
+                format!(
+                    "config.toml: '{}' can only be given on the command line",
+                    key
+                ),
+                error(content)
+            );
+        }
+        assert_eq!(
+            "config.toml: 'fuzzy' expects a string or a number",
+            error("fuzzy = 1.5")
+        );
+        assert!(error("engine = ").starts_with("config.toml: "));
+    }
+
+    #[test]
+    fn test_layers() {
+        let project = ConfigFile::parse(
+            "/repo/.drgreprc",
+            "ignore-case = true\nengine = \"auto\"\nregex-size-limit = 1000",
+        )
+        .unwrap();
+        let user = ConfigFile::parse(
+            "/home/config.toml",
+            "engine = \"fancy\"\nunique = false\ntype = [\"rust\", \"toml\"]",
+        )
+        .unwrap();
+        let mut parser = ArgParser::parse(["-k", "foo", "--regex-size-limit=5"], Some(&SPEC));
+        let sources = apply(&mut parser, &[project, user]);
+        assert_eq!(&Some("auto".to_string()), parser.get("engine"));
+        assert_eq!(&Some("5".to_string()), parser.get("regex-size-limit"));
+        assert_eq!(["rust", "toml"], parser.get_all("type"));
+
+        let debug = debug(&parser, &sources);
+        assert!(debug.contains("--key = foo (command line)\n"));
+        assert!(debug.contains("--regex-size-limit = 5 (command line)\n"));
+        assert!(debug.contains("--ignore-case = true (/repo/.drgreprc)\n"));
+        assert!(debug.contains("--engine = auto (/repo/.drgreprc)\n"));
+        assert!(debug.contains("--unique = false (/home/config.toml)\n"));
+        assert!(debug.contains("--type = rust, toml (/home/config.toml)\n"));
+    }
+
+    #[test]
+    fn test_command_line_overrides() {
+        let files = || {
+            [
+                ConfigFile::parse(
+                    "/repo/.drgreprc",
+                    "ignore-case = true\nword-regexp = false\nfixed-strings = true\npath = [\"src\"]",
+                )
+                .unwrap(),
+                ConfigFile::parse(
+                    "/home/config.toml",
+                    "smart-case = true\nbasic-regexp = true\nline-regexp = true",
+                )
+                .unwrap(),
+            ]
+        };
+
+        // The groups set on the command line or by the project config override the lower layers
+        let mut parser = ArgParser::parse(["-s", "-x", "foo", "docs"], Some(&SPEC));
+        let sources = apply(&mut parser, &files());
+        for name in [
+            "ignore-case",
+            "smart-case",
+            "word-regexp",
+            "basic-regexp",
+            "path",
+        ] {
+            assert!(!parser.has(name), "{}", name);
+        }
+        assert!(parser.has("s") && parser.has("x") && parser.has("fixed-strings"));
+        assert_eq!(["foo", "docs"], parser.positionals.as_slice());
+        assert_eq!(
+            vec!["fixed-strings"],
+            sources.into_keys().collect::<Vec<_>>()
+        );
+
+        // Without positional path, the paths of the config are searched
+        let mut parser = ArgParser::parse(["foo"], Some(&SPEC));
+        apply(&mut parser, &files());
+        assert_eq!(["/repo/src"], parser.get_all("path"));
+        assert!(parser.has("ignore-case") && !parser.has("smart-case"));
+        // A `false` flag doesn't override its group
+        assert!(parser.has("word-regexp") && parser.has("line-regexp"));
+        let mut parser = ArgParser::parse(["-k", "foo", "docs"], Some(&SPEC));
+        apply(&mut parser, &files());
+        assert!(!parser.has("path"));
+    }
+
+    #[test]
+    fn test_project_path() {
+        let root = create_temp_dir().unwrap();
+        let nested = root.path().join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(
+            None,
+            project_path(&nested).filter(|p| p.starts_with(root.path()))
+        );
+        fs::write(root.path().join("a").join(PROJECT_FILE), "unique = true").unwrap();
+        assert_eq!(
+            Some(root.path().join("a").join(PROJECT_FILE)),
+            project_path(&nested)
+        );
+        let file = ConfigFile::load(&project_path(&nested).unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(file.args().has("unique"));
+        assert!(ConfigFile::load(&root.path().join("missing.toml"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_paths_relative_to_the_file() {
+        let root = create_temp_dir().unwrap();
+        let nested = root.path().join("src").join("sub");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.path().join(PROJECT_FILE), "path = [\"src\", \"-\"]").unwrap();
+
+        // From a nested directory, the paths are resolved against the directory of the file
+        let file = ConfigFile::load(&project_path(&nested).unwrap())
+            .unwrap()
+            .unwrap();
+        let mut parser = ArgParser::parse(["foo"], Some(&SPEC));
+        apply(&mut parser, &[file]);
+        let src = root.path().join("src").to_string_lossy().into_owned();
+        assert_eq!([src.as_str(), "-"], parser.get_all("path"));
+        assert!(Path::new(&parser.get_all("path")[0]).is_dir());
+
+        // From the directory of the file, they stay as written
+        let file = ConfigFile::load_as(&root.path().join(PROJECT_FILE), Path::new(PROJECT_FILE))
+            .unwrap()
+            .unwrap();
+        let mut parser = ArgParser::parse(["foo"], Some(&SPEC));
+        apply(&mut parser, &[file]);
+        assert_eq!(["src", "-"], parser.get_all("path"));
+    }
+}
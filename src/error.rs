@@ -0,0 +1,112 @@
+//! # Error Module
+//!
+//! The errors reported by drgrep. A pattern error carries a `Diagnostic`, printed with a
+//! caret under the offending part of the pattern.
+//!
+//! The errors of the files met during a search don't stop it: they are reported on the
+//! standard error, unless `--no-messages` is given, and the search goes on.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::args::parser::ArgError;
+use crate::regex::diagnostic::Diagnostic;
+use crate::regex::pattern::PatternError;
+
+/// The errors of drgrep
+#[derive(Debug)]
+pub enum DrgrepError {
+    /// Invalid or conflicting command line arguments
+    Argument(String),
+    /// An option unknown to drgrep, an `ArgError::Unknown` with the closest known option
+    UnknownOption(ArgError),
+    /// A pattern that can't be compiled
+    Pattern(Box<Diagnostic>),
+    /// A file or a directory that can't be read
+    Io { path: PathBuf, source: io::Error },
+    /// A file that isn't valid UTF-8, like the binary files
+    Encoding { path: PathBuf },
+    /// An ignore file, like `.gitignore`, that can't be read
+    IgnoreFile { path: PathBuf, source: io::Error },
+    /// A config file, like `.drgreprc`, that isn't valid
+    ConfigFile { path: PathBuf, message: String },
+    /// A pattern failing while matching a file, like the fancy engine exceeding its
+    /// backtrack limit
+    Match { path: PathBuf, source: PatternError },
+    /// A search that went on after sources failing, each one already reported
+    Incomplete { errors: usize },
+}
+
+impl DrgrepError {
+    /// Creates an I/O error on the path
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        DrgrepError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for DrgrepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrgrepError::Argument(msg) => write!(f, "{}", msg),
+            DrgrepError::UnknownOption(error) => write!(f, "{}", error),
+            DrgrepError::Pattern(diagnostic) => write!(f, "{}", diagnostic),
+            DrgrepError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            DrgrepError::Encoding { path } => {
+                write!(f, "{}: the file isn't valid UTF-8", path.display())
+            }
+            DrgrepError::IgnoreFile { path, source } => write!(
+                f,
+                "{}: the ignore file can't be read: {}",
+                path.display(),
+                source
+            ),
+            DrgrepError::ConfigFile { path, message } => {
+                write!(f, "{}: {}", path.display(), message)
+            }
+            // The content given with --content has no path
+            DrgrepError::Match { path, source } if path.as_os_str().is_empty() => {
+                write!(f, "{}", source)
+            }
+            DrgrepError::Match { path, source } => write!(f, "{}: {}", path.display(), source),
+            DrgrepError::Incomplete { errors } => {
+                write!(f, "{} source(s) couldn't be searched", errors)
+            }
+        }
+    }
+}
+
+impl Error for DrgrepError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DrgrepError::Io { source, .. } | DrgrepError::IgnoreFile { source, .. } => Some(source),
+            DrgrepError::Match { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for DrgrepError {
+    fn from(msg: &str) -> DrgrepError {
+        DrgrepError::Argument(msg.to_string())
+    }
+}
+
+impl From<ArgError> for DrgrepError {
+    fn from(error: ArgError) -> DrgrepError {
+        match error {
+            ArgError::Unknown { .. } => DrgrepError::UnknownOption(error),
+            _ => DrgrepError::Argument(error.to_string()),
+        }
+    }
+}
+
+impl From<Diagnostic> for DrgrepError {
+    fn from(diagnostic: Diagnostic) -> DrgrepError {
+        DrgrepError::Pattern(Box::new(diagnostic))
+    }
+}
@@ -0,0 +1,419 @@
+//! # Extract Module
+//!
+//! Prints the matched values instead of the matching lines, replacing the
+//! `drgrep | sed | sort | uniq -c` pipelines:
+//!
+//! ```sh
+//! $ drgrep -o -r '[\w.]+@[\w.]+' -p docs # Every email
+//! $ drgrep --capture version -r 'version = "(?P<version>[^"]+)"' -p . --count-values
+//! ```
+//!
+//! `--unique` prints each value once, `--count-values` tallies the values and prints them
+//! by decreasing count at the end of the run.
+//!
+//! `--extract csv|tsv|json` turns the named groups of the regex into the columns of a table,
+//! with one row per match, after the `path` and `line` columns:
+//!
+//! ```sh
+//! $ drgrep -r '(?P<ts>\S+) (?P<level>\w+) (?P<msg>.*)' --extract csv -p app.log
+//! path,line,ts,level,msg
+//! app.log,1,2025-04-17T20:40:36,ERROR,"connection lost, retrying"
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use crate::regex::accents::Stripped;
+use crate::regex::pattern::{CaptureGroup, PatternError};
+use crate::Config;
+
+/// The values extracted from the matches
+#[derive(Debug, Clone, PartialEq)]
+pub enum Values {
+    /// The whole matches (`--only-matching`)
+    Matches,
+    /// A capture group of the regex (`--capture`)
+    Group(CaptureGroup),
+}
+
+/// Returns the byte spans of the values in the text
+fn spans(
+    config: &Config,
+    values: &Values,
+    text: &str,
+) -> Result<Vec<(usize, usize)>, PatternError> {
+    let mut spans: Vec<(usize, usize)> = match values {
+        Values::Group(group) => {
+            let mut spans = Vec::new();
+            if let Some(regex) = &config.regex {
+                regex.try_for_each_captures(text, |caps| {
+                    if let Some(m) = caps.group(group) {
+                        spans.push((m.start, m.end));
+                    }
+                })?;
+            }
+            spans
+        }
+        Values::Matches => {
+            if let Some(fuzzy) = &config.fuzzy {
+                fuzzy
+                    .find_all(text)
+                    .iter()
+                    .filter(|m| config.boundary.accepts(text, m.start, m.end))
+                    .map(|m| (m.start, m.end))
+                    .collect()
+            } else if let Some(fixed) = &config.fixed_strings {
+                fixed
+                    .find_all(text)
+                    .iter()
+                    .filter(|m| config.boundary.accepts(text, m.start, m.end))
+                    .map(|m| (m.start, m.end))
+                    .collect()
+            } else if let Some(set) = &config.pattern_set {
+                // The overlapping matches of several patterns make a single value
+                let matched = set.try_matches(text)?;
+                crate::match_spans(matched.iter().map(|i| &set.patterns()[*i]), text)?
+            } else if let Some(regex) = &config.regex {
+                regex
+                    .try_find_all(text)?
+                    .iter()
+                    .map(|m| (m.start, m.end))
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        }
+    };
+    spans.retain(|(start, end)| start < end);
+    Ok(spans)
+}
+
+/// Extracts the values of the content, in order
+///
+/// Each line is searched separately, unless the config is multiline. The runtime errors of
+/// the fancy engine are returned.
+pub fn values<'a>(
+    config: &Config,
+    values: &Values,
+    content: &'a str,
+) -> Result<Vec<&'a str>, PatternError> {
+    let units: Vec<&str> = if config.multiline {
+        vec![content]
+    } else {
+        content.lines().collect()
+    };
+    let mut found = Vec::new();
+    for unit in units {
+        if config.ignore_accents {
+            let stripped = Stripped::new(unit);
+            for (start, end) in spans(config, values, stripped.as_str())? {
+                let (start, end) = stripped.original_span(start, end);
+                found.push(&unit[start..end]);
+            }
+        } else {
+            found.extend(
+                spans(config, values, unit)?
+                    .into_iter()
+                    .map(|(start, end)| &unit[start..end]),
+            );
+        }
+    }
+    Ok(found)
+}
+
+/// The format of the tables of `--extract`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Tsv,
+    /// An array of objects, one per line
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown table format '{}'", name)),
+        }
+    }
+}
+
+/// A row of the table: the line of the match and its named groups, in the column order
+pub type Row<'a> = (usize, Vec<Option<&'a str>>);
+
+/// Extracts a row for each match of the regex of the config
+///
+/// The lines are numbered from 1, a multiline match is reported at its first line. The
+/// runtime errors of the fancy engine are returned.
+pub fn rows<'a>(config: &Config, content: &'a str) -> Result<Vec<Row<'a>>, PatternError> {
+    let Some(regex) = &config.regex else {
+        return Ok(Vec::new());
+    };
+    let names = regex.group_names();
+    let units: Vec<(usize, &str)> = if config.multiline {
+        vec![(0, content)]
+    } else {
+        content.lines().enumerate().collect()
+    };
+    let mut rows = Vec::new();
+    for (idx, unit) in units {
+        let stripped = config.ignore_accents.then(|| Stripped::new(unit));
+        let text = stripped.as_ref().map_or(unit, |s| s.as_str());
+        regex.try_for_each_captures(text, |captures| {
+            let cells = names
+                .iter()
+                .map(|name| {
+                    captures.name(name).map(|m| {
+                        let (start, end) = match &stripped {
+                            Some(stripped) => stripped.original_span(m.start, m.end),
+                            None => (m.start, m.end),
+                        };
+                        &unit[start..end]
+                    })
+                })
+                .collect();
+            // The line of the whole match, its groups can start after it
+            let line = match captures.get(0) {
+                Some(m) if config.multiline => text[..m.start].matches('\n').count(),
+                _ => idx,
+            };
+            rows.push((line + 1, cells));
+        })?;
+    }
+    Ok(rows)
+}
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escapes the tabs, the line breaks and the backslashes of a TSV field
+fn tsv_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Writes a JSON string literal
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A table printed row by row
+#[derive(Debug)]
+pub struct Table {
+    format: Format,
+    columns: Vec<String>,
+    rows: usize,
+}
+
+impl Table {
+    /// Creates a table with the `path` and `line` columns, then the given ones
+    pub fn new(format: Format, columns: &[&str]) -> Self {
+        let columns = ["path", "line"]
+            .iter()
+            .chain(columns)
+            .map(|c| c.to_string())
+            .collect();
+        Table {
+            format,
+            columns,
+            rows: 0,
+        }
+    }
+
+    /// Returns the header of the table, if any
+    pub fn header(&self) -> Option<String> {
+        match self.format {
+            Format::Csv => Some(
+                self.columns
+                    .iter()
+                    .map(|c| csv_field(c))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            Format::Tsv => Some(
+                self.columns
+                    .iter()
+                    .map(|c| tsv_field(c))
+                    .collect::<Vec<_>>()
+                    .join("\t"),
+            ),
+            Format::Json => Some("[".to_string()),
+        }
+    }
+
+    /// Formats a row, the missing groups being empty fields or `null`
+    pub fn format_row(&mut self, source: &str, (line, cells): &Row) -> String {
+        let line = line.to_string();
+        let fields = [Some(source), Some(line.as_str())]
+            .into_iter()
+            .chain(cells.iter().copied());
+        let row = match self.format {
+            Format::Csv => fields
+                .map(|f| csv_field(f.unwrap_or("")))
+                .collect::<Vec<_>>()
+                .join(","),
+            Format::Tsv => fields
+                .map(|f| tsv_field(f.unwrap_or("")))
+                .collect::<Vec<_>>()
+                .join("\t"),
+            Format::Json => {
+                let members: Vec<String> = self
+                    .columns
+                    .iter()
+                    .zip(fields)
+                    .enumerate()
+                    .map(|(i, (column, field))| {
+                        let value = match field {
+                            // The line number is a number
+                            Some(f) if i == 1 => f.to_string(),
+                            Some(f) => json_string(f),
+                            None => "null".to_string(),
+                        };
+                        format!("{}:{}", json_string(column), value)
+                    })
+                    .collect();
+                let separator = if self.rows > 0 { "," } else { "" };
+                format!("{}{{{}}}", separator, members.join(","))
+            }
+        };
+        self.rows += 1;
+        row
+    }
+
+    /// Returns the end of the table, if any
+    pub fn footer(&self) -> Option<String> {
+        match self.format {
+            Format::Json => Some("]".to_string()),
+            Format::Csv | Format::Tsv => None,
+        }
+    }
+}
+
+/// Prints the extracted values, deduped or tallied
+#[derive(Debug, Default)]
+pub struct Tally {
+    unique: bool,
+    count: bool,
+    seen: HashSet<String>,
+    counts: HashMap<String, usize>,
+}
+
+impl Tally {
+    pub fn new(unique: bool, count: bool) -> Self {
+        Tally {
+            unique,
+            count,
+            ..Tally::default()
+        }
+    }
+
+    /// Records a value, printed right away unless the values are counted
+    ///
+    /// The source prefixes the value when not empty, except for the unique values.
+    pub fn add(&mut self, source: &str, value: &str) {
+        if self.count {
+            *self.counts.entry(value.to_string()).or_insert(0) += 1;
+        } else if self.unique {
+            if self.seen.insert(value.to_string()) {
+                println!("{}", value);
+            }
+        } else if source.is_empty() {
+            println!("{}", value);
+        } else {
+            println!("{}:{}", source, value);
+        }
+    }
+
+    /// Returns the counted values, by decreasing count then in alphabetical order
+    pub fn counts(&self) -> Vec<(&str, usize)> {
+        let mut counts: Vec<(&str, usize)> = self
+            .counts
+            .iter()
+            .map(|(value, count)| (value.as_str(), *count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    }
+
+    /// Prints the counts, like `uniq -c`
+    pub fn finish(&self) {
+        for (value, count) in self.counts() {
+            println!("{:>7} {}", count, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_and_tsv() {
+        let mut table = Table::new(Format::Csv, &["level", "msg"]);
+        assert_eq!("path,line,level,msg", table.header().unwrap());
+        let row = (3, vec![Some("ERROR"), Some("lost, \"retrying\"")]);
+        assert_eq!(
+            "app.log,3,ERROR,\"lost, \"\"retrying\"\"\"",
+            table.format_row("app.log", &row)
+        );
+        assert_eq!(None, table.footer());
+
+        let mut table = Table::new(Format::Tsv, &["level", "msg"]);
+        let row = (1, vec![None, Some("a\tb")]);
+        assert_eq!("app.log\t1\t\ta\\tb", table.format_row("app.log", &row));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut table = Table::new(Format::Json, &["level", "msg"]);
+        assert_eq!("[", table.header().unwrap());
+        let row = (2, vec![Some("WARN"), None]);
+        assert_eq!(
+            r#"{"path":"a\\b.log","line":2,"level":"WARN","msg":null}"#,
+            table.format_row("a\\b.log", &row)
+        );
+        let row = (5, vec![Some("\"x\"\n"), Some("é")]);
+        assert_eq!(
+            r#",{"path":"","line":5,"level":"\"x\"\n","msg":"é"}"#,
+            table.format_row("", &row)
+        );
+        assert_eq!("]", table.footer().unwrap());
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut tally = Tally::new(false, true);
+        for value in ["b", "a", "b", "c", "a", "b"] {
+            tally.add("", value);
+        }
+        assert_eq!(vec![("b", 3), ("a", 2), ("c", 1)], tally.counts());
+    }
+}
@@ -0,0 +1,72 @@
+//! # File Types Module
+//!
+//! The file types of `--type`: while walking the directories, only the files with an
+//! extension of the given types are searched. The files given explicitly are always searched.
+//!
+//! ```rust
+//! use drgrep::file_types;
+//! use std::path::Path;
+//!
+//! assert!(file_types::matches(&["rust", "toml"], Path::new("src/lib.rs")));
+//! assert!(file_types::matches(&["md"], Path::new("README.MD")));
+//! assert!(!file_types::matches(&["python"], Path::new("setup.cfg")));
+//! ```
+
+use std::path::Path;
+
+/// The names of the types, in the order of `TYPES`
+pub const NAMES: &[&str] = &[
+    "c", "cpp", "css", "go", "html", "java", "js", "json", "md", "python", "rust", "sh", "toml",
+    "ts", "yaml",
+];
+
+/// The extensions of each type
+pub const TYPES: &[(&str, &[&str])] = &[
+    ("c", &["c", "h"]),
+    ("cpp", &["cpp", "cc", "cxx", "hpp", "hh", "hxx"]),
+    ("css", &["css", "scss"]),
+    ("go", &["go"]),
+    ("html", &["html", "htm"]),
+    ("java", &["java"]),
+    ("js", &["js", "mjs", "cjs", "jsx"]),
+    ("json", &["json"]),
+    ("md", &["md", "markdown"]),
+    ("python", &["py", "pyi"]),
+    ("rust", &["rs"]),
+    ("sh", &["sh", "bash", "zsh"]),
+    ("toml", &["toml"]),
+    ("ts", &["ts", "tsx", "mts", "cts"]),
+    ("yaml", &["yaml", "yml"]),
+];
+
+/// Whether the extension of the file, whatever its case, belongs to one of the types
+pub fn matches(types: &[&str], path: &Path) -> bool {
+    let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+    TYPES
+        .iter()
+        .filter(|(name, _)| types.contains(name))
+        .any(|(_, extensions)| extensions.iter().any(|e| e.eq_ignore_ascii_case(extension)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_follow_types() {
+        assert_eq!(
+            NAMES,
+            TYPES.iter().map(|(name, _)| *name).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(&["c"], Path::new("include/util.h")));
+        assert!(!matches(&["c"], Path::new("Makefile")));
+        assert!(!matches(&[], Path::new("main.rs")));
+        assert!(!matches(&["rust"], Path::new("rs")));
+    }
+}
@@ -131,6 +131,7 @@ impl GlobPattern {
     }
 
     /// Expand alternatives in a pattern like {a,b} to multiple patterns
+    #[allow(clippy::needless_range_loop)]
     fn expand_alternatives(pattern: &str) -> Vec<String> {
         let mut result = Vec::new();
         let mut current = String::new();
@@ -346,6 +347,7 @@ impl GlobPattern {
     }
 
     /// Recursive helper function to match text from a specific position.
+    #[allow(clippy::needless_range_loop, clippy::only_used_in_recursion)]
     fn matches_from_position(
         &self,
         text: &str,
@@ -29,40 +29,86 @@
 //! );
 //! }
 //! ```
+#![allow(clippy::needless_doctest_main)]
 
 pub mod args;
 pub mod color;
+pub mod config_file;
+pub mod error;
+pub mod extract;
+pub mod file_types;
 pub mod glob;
 pub mod regex;
+pub mod replace;
 pub mod temp_dir;
 
+use std::cell::{Cell, RefCell};
 use std::env;
-use std::fs::{DirEntry, ReadDir};
+use std::fs;
+use std::fs::DirEntry;
+use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
-use std::{error::Error, fs, path};
+
+use regex::pattern::PatternError;
 
 pub use args::parser::ArgParser;
 pub use color::config::Color;
 pub use color::printer::print_colored;
 pub use color::printer::print_partial_colored;
+pub use color::printer::print_segments;
 pub use color::printer::print_styled;
+pub use error::DrgrepError;
+pub use regex::fixed::FixedStrings;
 pub use regex::pattern::find;
 pub use regex::pattern::find_all;
 pub use regex::pattern::is_match;
 pub use regex::pattern::replace_all;
 pub use regex::pattern::RegexPattern;
 pub use regex::pattern::RegexPattern as SimplePattern;
+pub use regex::set::PatternSet;
 pub use utilities::read_stdin;
+pub use utilities::stdin_is_piped;
 
 /// The config struct
 #[derive(Debug)]
 pub struct Config<'a> {
     pub search_key: Option<&'a str>,
     pub search_content: Option<&'a str>,
-    pub file_path: Option<&'a str>,
+    /// The files and the directories to search, in order, the current directory when empty.
+    /// `-` is the standard input, read line by line
+    pub paths: Vec<&'a str>,
+    /// The types of the files searched in the directories, all of them when empty, see
+    /// `file_types`
+    pub types: Vec<&'a str>,
     pub regex: Option<regex::pattern::RegexPattern>,
+    pub pattern_set: Option<regex::set::PatternSet>,
+    pub fixed_strings: Option<regex::fixed::FixedStrings>,
+    /// The pattern of the case-insensitive key, compiled once for all the sources
+    pub key_pattern: Option<regex::pattern::RegexPattern>,
+    pub boundary: regex::pattern::Boundary,
     pub sensitive: bool,
-    path_is_dir: bool,
+    /// Matches the lines without their accents, see `regex::accents`
+    pub ignore_accents: bool,
+    /// Matches the key approximately, see `regex::fuzzy`
+    pub fuzzy: Option<regex::fuzzy::FuzzyPattern>,
+    /// Sorts the results of each source by edit distance
+    pub sort_by_distance: bool,
+    /// Runs the regexes over the whole content instead of each line
+    pub multiline: bool,
+    /// Prints the matched values instead of the lines, see `extract`
+    pub values: Option<extract::Values>,
+    /// Prints each extracted value once
+    pub unique: bool,
+    /// Tallies the extracted values
+    pub count_values: bool,
+    /// Prints the named groups of the regex as a table, see `extract`
+    pub extract: Option<extract::Format>,
+    pub replacement: Option<&'a str>,
+    pub in_place: bool,
+    pub write: bool,
+    pub interactive: bool,
+    /// Doesn't report the files that can't be read
+    pub no_messages: bool,
 }
 
 pub struct SearchResult<'a, 'b> {
@@ -70,8 +116,19 @@ pub struct SearchResult<'a, 'b> {
     pub word: &'b str,
     pub source: &'b str,
     pub idx: usize,
+    /// The last line of the result, after `idx` when a multiline match covers several lines
+    pub end_idx: usize,
+    /// The patterns which matched the line when searching with several patterns
+    pub patterns: Vec<&'b str>,
+    /// The byte spans of the matches in the line. Except for the key searches, which
+    /// split the line in words, `line` holds the segments of the line split at these spans
+    pub spans: Vec<(usize, usize)>,
+    /// The edit distances of the spans for the fuzzy searches
+    pub distances: Vec<usize>,
 }
 
+/// The help of the first versions of drgrep
+#[deprecated(note = "lists a few options only, use `drgrep::help()` instead")]
 pub static DEFAULT_MESSAGE: &str = "\
 drgrep is a CLI searching tool
 Usage:
@@ -89,276 +146,754 @@ drgrep --[args]/-[flag]
 
 pub static VERSION: &str = "v0.2.3";
 
+/// The options of drgrep, checked by `Config::new`
+pub static SPEC: args::parser::Spec = {
+    use args::parser::{ArgSpec, ValueType};
+    args::parser::Spec::new(&[
+        ArgSpec::flag("help").short('h').help("Print this help message"),
+        ArgSpec::flag("version")
+            .short('v')
+            .help("Print the current version of the drgrep software"),
+        ArgSpec::value("key", "KEY")
+            .short('k')
+            .help("The word that you want to search"),
+        ArgSpec::value("path", "PATH")
+            .short('p')
+            .repeatable()
+            .value_type(ValueType::Path)
+            .help("A file or a directory to search, can be repeated, the current directory by default"),
+        ArgSpec::value("type", "TYPE")
+            .short('t')
+            .repeatable()
+            .value_type(ValueType::Choice(file_types::NAMES))
+            .help("Only search the files of the type in the directories, can be repeated"),
+        ArgSpec::value("regex", "PATTERN")
+            .short('r')
+            .repeatable()
+            .help("The regex expression to use for matching"),
+        ArgSpec::value("regexp", "PATTERN")
+            .short('e')
+            .repeatable()
+            .help("A pattern to match, can be repeated to match any of several patterns"),
+        ArgSpec::value("file", "FILE")
+            .short('f')
+            .repeatable()
+            .value_type(ValueType::Path)
+            .help("A file containing the patterns to match, one per line"),
+        ArgSpec::flag("fixed-strings")
+            .short('F')
+            .help("Match the key and the patterns as literal strings, all at once"),
+        ArgSpec::flag("basic-regexp").short('G').help(
+            "Read the patterns as POSIX basic regular expressions, like grep: \\(a\\)\\{2\\}, \
+             [[:alpha:]]",
+        ),
+        ArgSpec::flag("extended-regexp")
+            .short('E')
+            .help("Read the patterns as POSIX extended regular expressions, like grep -E"),
+        ArgSpec::flag("word-regexp")
+            .short('w')
+            .help("Only match whole words"),
+        ArgSpec::flag("line-regexp")
+            .short('x')
+            .help("Only match whole lines"),
+        ArgSpec::value("regex-size-limit", "BYTES")
+            .value_type(ValueType::Number)
+            .help("The limit in bytes of the compiled regexes, for the huge patterns"),
+        ArgSpec::value("engine", "ENGINE")
+            .value_type(ValueType::Choice(&["default", "fancy", "pcre2", "auto"]))
+            .help(
+                "The regex engine: fancy supports the lookarounds and the backreferences, pcre2 \
+                 the Perl syntax, auto uses them only when the default engine can't compile the \
+                 pattern",
+            ),
+        ArgSpec::flag("multiline")
+            .short('U')
+            .help("Run the regexes over the whole content, so a match can span several lines"),
+        ArgSpec::flag("multiline-dotall")
+            .help("Used with -U to make the dot of the regexes match the line breaks too"),
+        ArgSpec::flag("no-unicode")
+            .help("Make \\w, \\d, \\s and \\b match ASCII characters only, (?u:.) for a Unicode dot"),
+        ArgSpec::flag("only-matching")
+            .short('o')
+            .help("Print each match on its own line instead of the matching lines"),
+        ArgSpec::value("capture", "N|NAME")
+            .help("Print a capture group of the regex instead of the matching lines"),
+        ArgSpec::flag("unique").help("Used with -o or --capture to print each value once"),
+        ArgSpec::flag("count-values")
+            .help("Used with -o or --capture to count each value, printed by decreasing count"),
+        ArgSpec::value("extract", "FORMAT")
+            .value_type(ValueType::Choice(&["csv", "tsv", "json"]))
+            .help("Print the named groups of the regex as a table with the path and the line of each match"),
+        ArgSpec::flag("no-messages")
+            .help("Don't report the files and the directories that can't be read"),
+        ArgSpec::value("content", "TEXT")
+            .short('c')
+            .help("The content to search, given as a string"),
+        ArgSpec::flag("sensitive").short('s').help(
+            "Match the case, the default when the DRGREP_SENSITIVE_CASE env variable is set",
+        ),
+        ArgSpec::flag("ignore-case")
+            .short('i')
+            .help("Ignore the case of the key and the regexes (Unicode case folding)"),
+        ArgSpec::flag("smart-case")
+            .short('S')
+            .env("DRGREP_SMART_CASE")
+            .help("Ignore the case unless the key or a pattern contains an uppercase letter"),
+        ArgSpec::value("fuzzy", "N")
+            .value_type(ValueType::Number)
+            .help("Match the substrings within N edits (Levenshtein distance) of the key"),
+        ArgSpec::value("sort", "ORDER")
+            .value_type(ValueType::Choice(&["distance"]))
+            .help("Show the closest fuzzy matches first, across all the files"),
+        ArgSpec::flag("ignore-accents").help(
+            "Ignore the accents of the key, the patterns and the text ('securite' matches \
+             'sécurité')",
+        ),
+        ArgSpec::value("replace", "TEMPLATE")
+            .help("The replacement template applied to every match (supports $1, ${name})"),
+        ArgSpec::flag("in-place").help(
+            "Rewrite the matching files with --replace, prints a unified diff by default (dry run)",
+        ),
+        ArgSpec::flag("write")
+            .help("Used with --in-place to actually write the changes to the files"),
+        ArgSpec::flag("interactive").help(
+            "Rewrite the matching files with --replace, asking a confirmation for every match",
+        ),
+        ArgSpec::value("undo", "ID")
+            .optional()
+            .help("Restore the files written by an in-place run, the last one by default"),
+        ArgSpec::flag("undo-list").help("List the in-place runs saved in the journal"),
+        ArgSpec::value("generate", "KIND")
+            .value_type(ValueType::Choice(&["man", "bash", "zsh", "fish", "powershell"]))
+            .help("Print the man page or the completion script of a shell"),
+        ArgSpec::flag("no-config")
+            .help("Ignore the user config file and the project .drgreprc"),
+        ArgSpec::flag("debug-config")
+            .help("Print each effective option and where its value comes from"),
+    ])
+};
+
+/// The description of drgrep, generating its help, its man page and its completions
+pub static PROGRAM: args::generate::Program = args::generate::Program {
+    name: "drgrep",
+    version: VERSION,
+    about: "drgrep is a CLI searching tool",
+    usage: &["[OPTIONS] PATTERN [PATH...]", "[OPTIONS] -k KEY [PATH...]"],
+    positionals: &[
+        (
+            "PATTERN",
+            "The regex to search, unless -k, -r, -e or -f gives the pattern",
+        ),
+        (
+            "PATH",
+            "The files and the directories to search, in order, `-` is the standard input",
+        ),
+    ],
+    spec: &SPEC,
+};
+
+/// Returns the `--help` message, generated from `SPEC`
+pub fn help() -> String {
+    args::generate::help(&PROGRAM)
+}
+
 impl<'a> Config<'a> {
-    pub fn new(args: &'a args::parser::ArgParser) -> Result<Self, &'static str> {
-        if !args.has("key")
-            && !args.has("k")
-            && !args.has("regex")
-            && !args.has("r")
-            && !args.has("content")
-            && !args.has("c")
+    pub fn new(args: &'a args::parser::ArgParser) -> Result<Self, DrgrepError> {
+        Self::with_lenient(args, false)
+    }
+
+    /// Like `new`, but ignores the unknown options when `lenient` is true, for the scripts
+    /// relying on that behavior (`DRGREP_LENIENT=1`)
+    pub fn with_lenient(
+        args: &'a args::parser::ArgParser,
+        lenient: bool,
+    ) -> Result<Self, DrgrepError> {
+        let args = if lenient {
+            SPEC.args_lenient(args)?
+        } else {
+            SPEC.args(args)?
+        };
+        let mut positionals = args.parser().positionals_ranked().into_iter();
+        // Like grep, the first positional is the pattern unless an option gives one
+        let positional_pattern = if ["key", "regex", "regexp", "file"]
+            .iter()
+            .any(|name| args.has(name))
+        {
+            None
+        } else {
+            positionals.next().map(|(_, pattern)| pattern)
+        };
+        if positional_pattern.is_none()
+            && !["key", "regex", "regexp", "file", "content"]
+                .iter()
+                .any(|name| args.has(name))
         {
-            return Err("no search key/regex provided");
+            return Err("no search key/regex provided".into());
         }
-        let search_key = match args.get("key") {
-            Some(value) => Some(value.as_str()),
-            None => args.get("k").as_ref().map(|v| v.as_str()),
+        let search_key = args.get("key");
+        // The -p paths and the positional ones are searched in the command line order
+        let mut paths = args.get_many_ranked("path");
+        paths.extend(positionals);
+        paths.sort_by_key(|(rank, _)| *rank);
+        // Like grep, the missing paths are reported while searching the others, see `run`
+        let mut paths: Vec<&str> = paths.into_iter().map(|(_, path)| path).collect();
+        let mut patterns: Vec<&str> = args.get_many("regex");
+        patterns.extend(args.get_many("regexp"));
+        patterns.extend(positional_pattern);
+        let pattern_files = args
+            .get_many("file")
+            .into_iter()
+            .map(|path| fs::read_to_string(path).map_err(|e| DrgrepError::io(path, e)))
+            .collect::<Result<Vec<String>, _>>()?;
+        // Empty lines are skipped, they would match every line
+        patterns.extend(
+            pattern_files
+                .iter()
+                .flat_map(|c| c.lines())
+                .filter(|l| !l.is_empty()),
+        );
+        let ignore_case = args.get_bool("ignore-case");
+        let smart_case = args.get_bool("smart-case");
+        let sensitive = if args.get_bool("sensitive") {
+            true
+        } else if ignore_case {
+            false
+        } else if smart_case {
+            search_key.is_some_and(|k| k.chars().any(char::is_uppercase))
+                || patterns.iter().any(|p| regex::pattern::has_uppercase(p))
+        } else {
+            env::var("DRGREP_SENSITIVE_CASE").is_ok()
         };
-        let mut is_dir = true;
-        let file_path = match args.get("path") {
-            Some(value) => {
-                let path = Path::new(value);
-                if path.is_file() {
-                    is_dir = false;
-                    Some(value.as_str())
-                } else if path.is_dir() {
-                    Some(value.as_str())
-                } else {
-                    None
-                }
+        // The regexes stay case-sensitive unless asked otherwise
+        let mut regex_insensitive = !sensitive && (ignore_case || smart_case);
+        let ignore_accents = args.get_bool("ignore-accents");
+        let multiline = args.get_bool("multiline");
+        let values = match args.get("capture") {
+            Some(group) => Some(extract::Values::Group(
+                group
+                    .parse()
+                    .map_err(|_| "--capture requires a group index or name")?,
+            )),
+            None if args.get_bool("only-matching") => Some(extract::Values::Matches),
+            None => None,
+        };
+        let unique = args.get_bool("unique");
+        let count_values = args.get_bool("count-values");
+        if (unique || count_values) && values.is_none() {
+            return Err("--unique/--count-values require --only-matching or --capture".into());
+        }
+        let extract: Option<extract::Format> = match args.get("extract") {
+            Some(format) => Some(
+                format
+                    .parse()
+                    .map_err(|_| "--extract must be one of csv, tsv or json")?,
+            ),
+            None => None,
+        };
+        if extract.is_some() && values.is_some() {
+            return Err("--extract can't be used with --only-matching or --capture".into());
+        }
+        let posix = match (
+            args.get_bool("basic-regexp"),
+            args.get_bool("extended-regexp"),
+        ) {
+            (true, true) => return Err("--basic-regexp and --extended-regexp are exclusive".into()),
+            (true, false) => Some(regex::posix::Syntax::Basic),
+            (false, true) => Some(regex::posix::Syntax::Extended),
+            (false, false) => None,
+        };
+        let fixed_strings = if args.get_bool("fixed-strings") {
+            if posix.is_some() {
+                return Err(
+                    "--fixed-strings can't be used with --basic-regexp/--extended-regexp".into(),
+                );
             }
-            None => {
-                let p = args.get("p").as_ref().map(|v| v.as_str());
-                if let Some(pth) = p {
-                    let path = Path::new(pth);
-                    if path.is_file() {
-                        is_dir = false;
-                        p
-                    } else if path.is_dir() {
-                        p
-                    } else {
-                        None
-                    }
-                } else {
-                    None
+            let literals = search_key
+                .iter()
+                .copied()
+                .chain(patterns.drain(..))
+                .map(|l| strip_accents_if(ignore_accents, l));
+            match regex::fixed::FixedStrings::new(literals, !sensitive) {
+                Ok(val) => Some(val),
+                Err(_) => {
+                    return Err("Error during the creating of the fixed strings matcher".into())
                 }
             }
+        } else {
+            None
         };
-        let regex = match args.get("regex") {
-            Some(value) => match regex::pattern::RegexPattern::new(value) {
-                Ok(val) => Some(val),
-                Err(_) => return Err("Error during the creating of the current regex"),
-            },
-            None => {
-                if let Some(value) = args.get("r") {
-                    if let Ok(r) = regex::pattern::RegexPattern::new(value) {
-                        Some(r)
-                    } else {
-                        return Err("Error during the creating of the current regex");
+        let boundary = if args.get_bool("line-regexp") {
+            regex::pattern::Boundary::Line
+        } else if args.get_bool("word-regexp") {
+            regex::pattern::Boundary::Word
+        } else {
+            regex::pattern::Boundary::None
+        };
+        // The patterns as written, or translated from POSIX, to explain the errors
+        let mut sources: Vec<String> = patterns
+            .iter()
+            .map(|p| match posix {
+                Some(syntax) => regex::posix::translate(p, syntax)
+                    .map_err(|e| regex::diagnostic::Diagnostic::new(p, &e)),
+                None => Ok(p.to_string()),
+            })
+            .collect::<Result<_, _>>()?;
+        let mut patterns: Vec<String> = sources
+            .iter()
+            .map(|p| boundary.wrap(&strip_accents_if(ignore_accents, p)))
+            .collect();
+        let fuzzy = match args.get_usize("fuzzy") {
+            Some(distance) => {
+                let key = match (search_key, patterns.is_empty(), &fixed_strings) {
+                    (Some(key), true, None) => strip_accents_if(ignore_accents, key),
+                    _ => return Err("--fuzzy only applies to a single --key".into()),
+                };
+                match regex::fuzzy::FuzzyPattern::new(&key, distance, !sensitive) {
+                    Ok(val) => Some(val),
+                    Err(_) => {
+                        return Err("the --fuzzy distance must be lower than the key length".into())
                     }
-                } else {
-                    None
                 }
             }
+            None => None,
         };
-        let search_content = match args.get("content") {
-            Some(c) => Some(c.as_str()),
-            None => {
-                if let Some(c) = args.get("c") {
-                    is_dir = false;
-                    Some(c.as_str())
-                } else {
-                    None
-                }
+        let sort_by_distance = match args.get("sort") {
+            Some(_) if fuzzy.is_some() => true,
+            Some(_) => return Err("--sort distance requires --fuzzy".into()),
+            None => false,
+        };
+        if multiline && (fixed_strings.is_some() || fuzzy.is_some()) {
+            return Err("--multiline only applies to the key and the regexes".into());
+        }
+        // A delimited key is searched as a regex, so its highlighting follows the boundaries.
+        // So is a key without accents, its matches being mapped back to the accented lines
+        if let (Some(key), true, None, None) = (
+            search_key,
+            patterns.is_empty()
+                && (boundary != regex::pattern::Boundary::None
+                    || ignore_accents
+                    || multiline
+                    || values.is_some()),
+            &fixed_strings,
+            &fuzzy,
+        ) {
+            // Like the key searches, ignores the case unless asked otherwise
+            regex_insensitive = !sensitive;
+            sources.push(::regex::escape(key));
+            let key = ::regex::escape(&strip_accents_if(ignore_accents, key));
+            patterns.push(boundary.wrap(&key));
+        }
+        let engine: regex::pattern::Engine = match args.get("engine") {
+            Some(name) => name
+                .parse()
+                .map_err(|_| "--engine must be one of default, fancy, pcre2 or auto")?,
+            // The POSIX back-references need a backtracking engine
+            None if posix.is_some() && cfg!(any(feature = "fancy", feature = "pcre2")) => {
+                regex::pattern::Engine::Auto
             }
+            None => regex::pattern::Engine::Default,
         };
-        let sensitive = match args.get("sensitive") {
-            Some(_) => true,
-            None => match args.get("s") {
-                Some(_) => true,
-                None => env::var("DRGREP_SENSITIVE_CASE").is_ok(),
+        if !engine.is_available() {
+            return Err("this --engine isn't available in this build of drgrep".into());
+        }
+        let mut builder = regex::pattern::RegexPatternBuilder::new("");
+        builder
+            .engine(engine)
+            .case_insensitive(regex_insensitive)
+            .multi_line(multiline)
+            .dot_matches_new_line(args.get_bool("multiline-dotall"))
+            .unicode(!args.get_bool("no-unicode"));
+        if let Some(limit) = args.get_usize("regex-size-limit") {
+            builder.size_limit(limit);
+        }
+        let (regex, pattern_set) = match &patterns[..] {
+            [] => (None, None),
+            [pattern] => match builder.pattern(pattern).build() {
+                Ok(val) => (Some(val), None),
+                Err(e) => return Err(regex::diagnostic::Diagnostic::new(&sources[0], &e).into()),
+            },
+            _ => match regex::set::PatternSet::with_builder(&patterns, &builder) {
+                Ok(val) => (None, Some(val)),
+                Err(e) => {
+                    // Explains the first pattern failing on its own
+                    let diagnostic = patterns
+                        .iter()
+                        .zip(&sources)
+                        .find_map(|(pattern, source)| {
+                            let e = builder.pattern(pattern).build().err()?;
+                            Some(regex::diagnostic::Diagnostic::new(source, &e))
+                        })
+                        .unwrap_or_else(|| {
+                            regex::diagnostic::Diagnostic::new(&sources.join(" | "), &e)
+                        });
+                    return Err(diagnostic.into());
+                }
             },
         };
+        if let Some(extract::Values::Group(group)) = &values {
+            match &regex {
+                Some(regex) if regex.has_group(group) => (),
+                Some(_) => return Err("--capture refers to a group missing from the regex".into()),
+                None => return Err("--capture requires a single regex".into()),
+            }
+        }
+        if extract.is_some() {
+            match &regex {
+                Some(regex) if !regex.group_names().is_empty() => (),
+                Some(_) => return Err("--extract requires a regex with named groups".into()),
+                None => return Err("--extract requires a single regex".into()),
+            }
+        }
+        let mut search_content = args.get("content");
+        // `-c @` read the standard input before `-`, kept for the old scripts
+        if search_content == Some("@") && paths.is_empty() {
+            search_content = None;
+            paths.push("-");
+        }
+        if search_content.is_some() && !paths.is_empty() {
+            return Err("--content can't be used with paths".into());
+        }
+        let replacement = args.get("replace");
+        let interactive = args.get_bool("interactive");
+        let in_place = args.get_bool("in-place") || interactive;
+        let write = args.get_bool("write");
+        if in_place && replacement.is_none() {
+            return Err("--in-place/--interactive requires a --replace template".into());
+        }
+        if replacement.is_some() && !in_place {
+            return Err("--replace must be used with --in-place or --interactive".into());
+        }
+        if in_place && paths.contains(&"-") {
+            return Err("--in-place/--interactive can't rewrite the standard input".into());
+        }
+        // --interactive writes the accepted matches on its own, --write is only for --in-place
+        if write && !args.get_bool("in-place") {
+            return Err("--write must be used with --in-place".into());
+        }
+        if ignore_accents && in_place {
+            return Err("--ignore-accents can't be used with --in-place or --interactive".into());
+        }
+        if fuzzy.is_some() && in_place {
+            return Err("--fuzzy can't be used with --in-place or --interactive".into());
+        }
+        if multiline && in_place {
+            return Err("--multiline can't be used with --in-place or --interactive".into());
+        }
+        if values.is_some() && in_place {
+            return Err(
+                "--only-matching/--capture can't be used with --in-place or --interactive".into(),
+            );
+        }
+        if extract.is_some() && in_place {
+            return Err("--extract can't be used with --in-place or --interactive".into());
+        }
 
+        let key_pattern = match search_key {
+            Some(key) if !sensitive => Some(
+                RegexPattern::literal(key, true).expect("an escaped literal is a valid pattern"),
+            ),
+            _ => None,
+        };
         Ok(Config {
             search_key,
-            file_path,
+            paths,
+            types: args.get_many("type"),
             sensitive,
             regex,
+            pattern_set,
+            fixed_strings,
+            key_pattern,
+            boundary,
+            ignore_accents,
+            fuzzy,
+            sort_by_distance,
+            multiline,
+            values,
+            unique,
+            count_values,
+            extract,
             search_content,
-            path_is_dir: is_dir,
+            replacement,
+            in_place,
+            write,
+            interactive,
+            no_messages: args.get_bool("no-messages"),
         })
     }
+
+    /// Whether a file met while walking the directories is searched, according to its type
+    pub fn has_file_type(&self, path: &Path) -> bool {
+        self.types.is_empty() || file_types::matches(&self.types, path)
+    }
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let ignore = utilities::GitIgnoreFiles::load();
+/// Returns the text without its accents when `ignore_accents` is set
+fn strip_accents_if(ignore_accents: bool, text: &str) -> String {
+    if ignore_accents {
+        regex::accents::strip_accents(text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Prints a search result, with its source when searching in files
+fn print_result(result: &SearchResult, show_source: bool) {
+    if show_source {
+        print_colored!(
+            format!("source: {}", result.source).as_str(),
+            color::config::Color::BRIGHT_BLUE
+        );
+    }
+    let line = if result.end_idx > result.idx {
+        format!("lines: {}-{}", result.idx, result.end_idx)
+    } else {
+        format!("line: {}", result.idx)
+    };
+    print_colored!(line.as_str(), color::config::Color::RED);
+    if !result.patterns.is_empty() {
+        print_colored!(
+            format!("pattern: {}", result.patterns.join(" | ")).as_str(),
+            color::config::Color::MAGENTA
+        );
+    }
+    if !result.distances.is_empty() {
+        // Each span is highlighted in its own segment
+        let matches: Vec<String> = result
+            .line
+            .iter()
+            .filter(|(_, color)| *color == color::config::Color::BRIGHT_YELLOW)
+            .zip(&result.distances)
+            .map(|((text, _), distance)| format!("{} (distance {})", text, distance))
+            .collect();
+        print_colored!(
+            format!("fuzzy: {}", matches.join(" | ")).as_str(),
+            color::config::Color::CYAN
+        );
+    }
+    // The key searches split the line in words, the others at the match boundaries
+    if result.word.is_empty() {
+        print_segments(&result.line);
+    } else {
+        print_partial_colored!(&result.line);
+    }
+    println!("=================================\n");
+}
+
+/// Searches the content with the patterns, the regex or the key of the config
+///
+/// Fails with the runtime error of the fancy engine, like exceeding its backtrack limit.
+fn search_with_config<'a, 'b>(
+    config: &'b Config,
+    source: &'b str,
+    content: &'a str,
+) -> Result<Vec<SearchResult<'a, 'b>>, PatternError> {
+    let accents = config.ignore_accents;
+    if let Some(fuzzy) = &config.fuzzy {
+        let mut results = search_lines(source, content, accents, |l| {
+            Ok(fuzzy_hits(fuzzy, config.boundary, l))
+        })?;
+        if config.sort_by_distance {
+            // Stable, the lines with the same distance keep their order
+            results.sort_by_key(|r| r.distances.iter().min().copied());
+        }
+        Ok(results)
+    } else if let Some(fixed) = &config.fixed_strings {
+        search_lines(source, content, accents, |l| {
+            Ok(fixed_hits(fixed, config.boundary, l))
+        })
+    } else if let Some(set) = &config.pattern_set {
+        if config.multiline {
+            search_buffer(source, content, accents, |t| set_buffer_matches(set, t))
+        } else {
+            search_lines(source, content, accents, |l| set_hits(set, l))
+        }
+    } else if let Some(reg) = &config.regex {
+        if config.multiline {
+            search_buffer(source, content, accents, |t| regex_buffer_matches(reg, t))
+        } else {
+            search_lines(source, content, accents, |l| regex_hits(reg, l))
+        }
+    } else if let Some(key) = config.search_key {
+        Ok(match &config.key_pattern {
+            _ if config.sensitive => search_word_sensitive_case(key, source, content),
+            Some(pattern) => search_word_with_pattern(pattern, key, source, content),
+            None => search_word_insensitive_case(key, source, content),
+        })
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Reports each line of the reader as it comes with its offset, the number of the lines before
+/// it. A line which isn't valid UTF-8 is decoded lossily instead of ending the input
+fn report_lines(mut reader: impl BufRead, mut report: impl FnMut(&str, usize)) -> io::Result<()> {
+    let mut line = Vec::new();
+    for offset in 0.. {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        report(&String::from_utf8_lossy(&line), offset);
+    }
+    Ok(())
+}
+
+pub fn run(config: Config) -> Result<(), DrgrepError> {
+    if config.in_place {
+        return replace::run(&config);
+    }
+    // The failures are counted even when --no-messages hides them, for the exit code
+    let errors = Cell::new(0);
+    let warn = |e: DrgrepError| {
+        errors.set(errors.get() + 1);
+        if !config.no_messages {
+            eprintln!("drgrep: {}", e);
+        }
+    };
+    let (ignore, ignore_error) = utilities::GitIgnoreFiles::load();
+    if let Some(e) = ignore_error {
+        warn(e);
+    }
     let current_dir = if let Ok(p) = env::current_dir() {
         p
     } else {
         PathBuf::new()
     };
-    if !config.path_is_dir {
-        if let Some(val) = config.file_path {
-            let file_path = path::Path::new(val);
-            let content = fs::read_to_string(file_path)?;
-            if let Some(reg) = config.regex {
-                for result in search_with_regex(&reg, val, &content) {
-                    print_colored!(
-                        format!("source: {}", result.source).as_str(),
-                        color::config::Color::BRIGHT_BLUE
-                    );
-                    print_colored!(
-                        format!("line: {}", result.idx).as_str(),
-                        color::config::Color::RED
-                    );
-                    print_partial_colored!(&result.line);
-                    println!("=================================\n");
+    let tally = RefCell::new(extract::Tally::new(config.unique, config.count_values));
+    let table = RefCell::new(config.extract.map(|format| {
+        let names = config
+            .regex
+            .as_ref()
+            .map_or(Vec::new(), |r| r.group_names());
+        extract::Table::new(format, &names)
+    }));
+    if let Some(header) = table.borrow().as_ref().and_then(|t| t.header()) {
+        println!("{}", header);
+    }
+    // With --sort distance the sources with matches are kept, their results are sorted
+    // together and printed once every source is searched
+    let sorted = RefCell::new(Vec::new());
+    // Prints the results of a source, or the values or the rows extracted from them.
+    // The lines are numbered after `offset`, the lines of the source already reported
+    // A pattern failing at runtime is reported, the source being skipped
+    let report = |source: &str, content: &str, show_source: bool, offset: usize| {
+        let reported = if let Some(table) = table.borrow_mut().as_mut() {
+            let source = if show_source { source } else { "" };
+            extract::rows(&config, content).map(|rows| {
+                for (line, cells) in rows {
+                    println!("{}", table.format_row(source, &(line + offset, cells)));
                 }
-                return Ok(());
-            } else if config.sensitive {
-                if let Some(key) = config.search_key {
-                    for result in search_word_sensitive_case(key, val, &content) {
-                        print_colored!(
-                            format!("source: {}", result.source).as_str(),
-                            color::config::Color::BRIGHT_BLUE
-                        );
-                        print_colored!(
-                            format!("line: {}", result.idx).as_str(),
-                            color::config::Color::RED
-                        );
-                        print_partial_colored!(&result.line);
-                        println!("=================================\n");
-                    }
+            })
+        } else if let Some(values) = &config.values {
+            let mut tally = tally.borrow_mut();
+            extract::values(&config, values, content).map(|values| {
+                for value in values {
+                    tally.add(if show_source { source } else { "" }, value);
                 }
-                return Ok(());
-            } else if let Some(key) = config.search_key {
-                for result in search_word_insensitive_case(key, val, &content) {
-                    print_colored!(
-                        format!("source: {}", result.source).as_str(),
-                        color::config::Color::BRIGHT_BLUE
-                    );
-                    print_colored!(
-                        format!("line: {}", result.idx).as_str(),
-                        color::config::Color::RED
-                    );
-                    print_partial_colored!(&result.line);
-                    println!("=================================\n");
+            })
+        } else if config.sort_by_distance {
+            search_with_config(&config, source, content).map(|results| {
+                if !results.is_empty() {
+                    let kept = (source.to_string(), content.to_string(), show_source);
+                    sorted.borrow_mut().push(kept);
                 }
-            }
-            return Ok(());
-        } else if let Some(content) = config.search_content {
-            // println!("search content {}", content);
-            if let Some(key) = config.search_key {
-                if config.sensitive {
-                    for result in search_word_sensitive_case(key, "", content) {
-                        print_colored!(
-                            format!("line: {}", result.idx).as_str(),
-                            color::config::Color::RED
-                        );
-                        print_partial_colored!(&result.line);
-                        println!("=================================\n");
-                    }
-                } else {
-                    for result in search_word_insensitive_case(key, "", content) {
-                        print_colored!(
-                            format!("line: {}", result.idx).as_str(),
-                            color::config::Color::RED
-                        );
-                        print_partial_colored!(&result.line);
-                        println!("=================================\n");
-                    }
-                }
-            } else if let Some(reg) = config.regex {
-                for result in search_with_regex(&reg, "", content) {
-                    print_colored!(
-                        format!("line: {}", result.idx).as_str(),
-                        color::config::Color::RED
-                    );
-                    print_partial_colored!(&result.line);
-                    println!("=================================\n");
+            })
+        } else {
+            search_with_config(&config, source, content).map(|results| {
+                for mut result in results {
+                    result.idx += offset;
+                    result.end_idx += offset;
+                    print_result(&result, show_source);
                 }
+            })
+        };
+        if let Err(e) = reported {
+            warn(DrgrepError::Match {
+                path: source.into(),
+                source: e,
+            });
+        }
+    };
+    // The standard input is searched as it comes, so `tail -f app.log | drgrep -k ERROR`
+    // prints the matches live. The multiline and the sorted searches need the whole input
+    let report_stdin = |show_source: bool| {
+        let source = "(standard input)";
+        if config.multiline || config.sort_by_distance {
+            match utilities::read_stdin() {
+                Ok(content) => report(source, &content, show_source, 0),
+                Err(e) => warn(DrgrepError::io(source, e)),
             }
+            return;
         }
-        return Ok(());
-    }
+        let reported = report_lines(io::stdin().lock(), |line, offset| {
+            report(source, line, show_source, offset)
+        });
+        if let Err(e) = reported {
+            warn(DrgrepError::io(source, e));
+        }
+    };
 
-    let files: ReadDir;
-    if let Some(val) = config.file_path {
-        files = fs::read_dir(Path::new(val))?;
+    if let (true, Some(content)) = (config.paths.is_empty(), config.search_content) {
+        report("", content, false, 0);
     } else {
-        files = fs::read_dir(Path::new("./"))?;
-    }
-
-    let handle_files: &dyn Fn(&DirEntry) = &|f| {
-        if let Ok(f_type) = f.file_type() {
-            if f_type.is_file() && !ignore.is_ignored(&f.path(), &current_dir) {
-                if let Ok(content) = utilities::can_read_to_utf8(&f.path()) {
-                    if let Some(reg) = &config.regex {
-                        for result in search_with_regex(reg, f.path().to_str().unwrap(), &content) {
-                            print_colored!(
-                                format!("source: {}", result.source).as_str(),
-                                color::config::Color::BRIGHT_BLUE
-                            );
-                            print_colored!(
-                                format!("line: {}", result.idx).as_str(),
-                                color::config::Color::RED
-                            );
-                            print_partial_colored!(&result.line);
-                            println!("=================================\n");
-                        }
-                        return;
-                    }
-                    if config.sensitive {
-                        if let Some(key) = config.search_key {
-                            for result in search_word_sensitive_case(
-                                key,
-                                f.path().to_str().unwrap(),
-                                &content,
-                            ) {
-                                print_colored!(
-                                    format!("source: {}", result.source).as_str(),
-                                    color::config::Color::BRIGHT_BLUE
-                                );
-                                print_colored!(
-                                    format!("line: {}", result.idx).as_str(),
-                                    color::config::Color::RED
-                                );
-                                print_partial_colored!(&result.line);
-                                println!("=================================\n");
-                            }
-                        }
-                    } else if let Some(key) = config.search_key {
-                        for result in
-                            search_word_insensitive_case(key, f.path().to_str().unwrap(), &content)
-                        {
-                            print_colored!(
-                                format!("source: {}", result.source).as_str(),
-                                color::config::Color::BRIGHT_BLUE
-                            );
-                            print_colored!(
-                                format!("line: {}", result.idx).as_str(),
-                                color::config::Color::RED
-                            );
-                            print_partial_colored!(&result.line);
-                            println!("=================================\n");
-                        }
+        let handle_files: &dyn Fn(&DirEntry) = &|f| {
+            if let Ok(f_type) = f.file_type() {
+                if f_type.is_file() && config.has_file_type(&f.path()) {
+                    let path = f.path();
+                    match utilities::can_read_to_utf8(&path) {
+                        Ok(content) => report(&path.to_string_lossy(), &content, true, 0),
+                        // The binary files are skipped silently while walking
+                        Err(DrgrepError::Encoding { .. }) => (),
+                        Err(e) => warn(e),
                     }
                 }
             }
-        }
-    };
+        };
 
-    files
-        .filter(|f| {
-            if let Ok(entry) = f {
-                // println!("entry: {}", entry.path().display());
-                !ignore.is_ignored(&entry.path(), &current_dir)
+        let roots = if config.paths.is_empty() {
+            vec!["./"]
+        } else {
+            config.paths.clone()
+        };
+        // The paths are searched in order, the files given explicitly are read even if ignored
+        for root in roots {
+            if root == "-" {
+                // The source is shown when other paths are searched along the piped input
+                report_stdin(config.paths.len() > 1);
+            } else if Path::new(root).is_dir() {
+                utilities::walk(Path::new(root), &ignore, &current_dir, handle_files, &warn);
             } else {
-                false
-            }
-        })
-        .for_each(|el| {
-            if let Ok(f) = el {
-                if f.path().is_file() {
-                    handle_files(&f);
-                    return;
+                match utilities::can_read_to_utf8(Path::new(root)) {
+                    Ok(content) => report(root, &content, true, 0),
+                    Err(e) => warn(e),
                 }
-                utilities::visit_dirs(&f.path(), handle_files)
-                    .unwrap_or_else(|err| panic!("{}", err))
             }
-        });
-    Ok(())
+        }
+    }
+    let sorted = sorted.take();
+    let mut results: Vec<(SearchResult, bool)> = sorted
+        .iter()
+        .flat_map(|(source, content, show_source)| {
+            // The kept sources were already searched without error
+            search_with_config(&config, source, content)
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |result| (result, *show_source))
+        })
+        .collect();
+    // Stable, the results with the same distance keep the order of the sources
+    results.sort_by_key(|(result, _)| result.distances.iter().min().copied());
+    for (result, show_source) in &results {
+        print_result(result, *show_source);
+    }
+    tally.borrow().finish();
+    if let Some(footer) = table.borrow().as_ref().and_then(|t| t.footer()) {
+        println!("{}", footer);
+    }
+    match errors.get() {
+        0 => Ok(()),
+        errors => Err(DrgrepError::Incomplete { errors }),
+    }
 }
 
 pub fn search_sensitive_case<'a>(search_content: &str, content: &'a str) -> Vec<&'a str> {
@@ -368,14 +903,13 @@ pub fn search_sensitive_case<'a>(search_content: &str, content: &'a str) -> Vec<
         .collect()
 }
 
+/// Searches the lines containing the key, whatever their case
+///
+/// Relies on the Unicode case folding of the regex engine instead of lowercasing each line.
 pub fn search_insensitive_case<'a>(search_content: &str, content: &'a str) -> Vec<&'a str> {
-    let mut result: Vec<&str> = Vec::new();
-    for line in content.lines() {
-        if line.to_lowercase().contains(&search_content.to_lowercase()) {
-            result.push(line);
-        }
-    }
-    result
+    let key =
+        RegexPattern::literal(search_content, true).expect("an escaped literal is a valid pattern");
+    content.lines().filter(|line| key.is_match(line)).collect()
 }
 
 pub fn search_word_sensitive_case<'a, 'b>(
@@ -405,6 +939,10 @@ pub fn search_word_sensitive_case<'a, 'b>(
                 word: key,
                 source,
                 idx: idx + 1, // Using one-based line numbers
+                end_idx: idx + 1,
+                patterns: Vec::new(),
+                spans: Vec::new(),
+                distances: Vec::new(),
             }
         })
         .collect()
@@ -414,17 +952,28 @@ pub fn search_word_insensitive_case<'a, 'b>(
     key: &'b str,
     source: &'b str,
     content: &'a str,
+) -> Vec<SearchResult<'a, 'b>> {
+    let pattern = RegexPattern::literal(key, true).expect("an escaped literal is a valid pattern");
+    search_word_with_pattern(&pattern, key, source, content)
+}
+
+/// Searches the lines matching the pattern of the key, highlighting the matching words
+fn search_word_with_pattern<'a, 'b>(
+    pattern: &RegexPattern,
+    key: &'b str,
+    source: &'b str,
+    content: &'a str,
 ) -> Vec<SearchResult<'a, 'b>> {
     content
         .lines()
         .enumerate() // Provides a line index automatically
-        .filter(|(_, line)| line.to_lowercase().contains(&key.to_lowercase()))
+        .filter(|(_, line)| pattern.is_match(line))
         .map(|(idx, line)| {
             // For example, split the line and highlight matching words
             let parts = line
                 .split(' ')
                 .map(|w| {
-                    let color = if w.to_lowercase().contains(&key.to_lowercase()) {
+                    let color = if pattern.is_match(w) {
                         color::config::Color::BRIGHT_YELLOW
                     } else {
                         color::config::Color::WHITE
@@ -437,48 +986,350 @@ pub fn search_word_insensitive_case<'a, 'b>(
                 word: key,
                 source,
                 idx: idx + 1, // Using one-based line numbers
+                end_idx: idx + 1,
+                patterns: Vec::new(),
+                spans: Vec::new(),
+                distances: Vec::new(),
             }
         })
         .collect()
 }
 
+/// Searches the lines matching the regex
+///
+/// The lines failing at runtime in the fancy engine, like exceeding its backtrack limit,
+/// are skipped.
 pub fn search_with_regex<'a, 'b>(
     regex: &RegexPattern,
     source: &'b str,
     content: &'a str,
 ) -> Vec<SearchResult<'a, 'b>> {
-    content
-        .lines()
-        .enumerate()
-        .filter(|(_, l)| regex.is_match(l))
-        .map(|(idx, line)| {
-            let parts = line
-                .split(' ')
-                .map(|w| {
-                    let color = if regex.is_match(w.to_lowercase().as_str()) {
-                        color::config::Color::BRIGHT_YELLOW
-                    } else {
-                        color::config::Color::WHITE
-                    };
-                    (w, color)
-                })
+    search_lines(source, content, false, |l| {
+        Ok(regex_hits(regex, l).unwrap_or(None))
+    })
+    .unwrap_or_default()
+}
+
+/// The matches found in a line
+#[derive(Default)]
+struct Hits<'b> {
+    spans: Vec<(usize, usize)>,
+    /// The patterns which matched
+    patterns: Vec<&'b str>,
+    /// The edit distances of the spans, for the fuzzy searches
+    distances: Vec<usize>,
+}
+
+/// Searches the lines for which `find` returns hits, highlighting their spans
+///
+/// With `ignore_accents`, `find` gets the lines without their accents and the spans it
+/// returns are mapped back to the original lines. The first error of `find` is returned.
+fn search_lines<'a, 'b>(
+    source: &'b str,
+    content: &'a str,
+    ignore_accents: bool,
+    find: impl Fn(&str) -> Result<Option<Hits<'b>>, PatternError>,
+) -> Result<Vec<SearchResult<'a, 'b>>, PatternError> {
+    let mut results = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        let hits = if ignore_accents {
+            let stripped = regex::accents::Stripped::new(line);
+            let Some(hits) = find(stripped.as_str())? else {
+                continue;
+            };
+            let spans = hits
+                .spans
+                .into_iter()
+                .map(|(start, end)| stripped.original_span(start, end))
                 .collect();
+            Hits { spans, ..hits }
+        } else {
+            let Some(hits) = find(line)? else {
+                continue;
+            };
+            hits
+        };
+        results.push(SearchResult {
+            line: highlight_spans(line, &hits.spans),
+            word: "",
+            source,
+            idx: idx + 1,
+            end_idx: idx + 1,
+            patterns: hits.patterns,
+            spans: hits.spans,
+            distances: hits.distances,
+        });
+    }
+    Ok(results)
+}
+
+/// A match over the whole content, with the pattern which found it for the sets
+type BufferMatch<'b> = (usize, usize, Option<&'b str>);
+
+/// Searches the whole content at once, each result covering the lines of its matches
+///
+/// The matches sharing a line are reported together, `idx` and `end_idx` being the first
+/// and the last line they cover. The error of `find` is returned.
+fn search_buffer<'a, 'b>(
+    source: &'b str,
+    content: &'a str,
+    ignore_accents: bool,
+    find: impl Fn(&str) -> Result<Vec<BufferMatch<'b>>, PatternError>,
+) -> Result<Vec<SearchResult<'a, 'b>>, PatternError> {
+    let mut matches = if ignore_accents {
+        let stripped = regex::accents::Stripped::new(content);
+        find(stripped.as_str())?
+            .into_iter()
+            .map(|(start, end, pattern)| {
+                let (start, end) = stripped.original_span(start, end);
+                (start, end, pattern)
+            })
+            .collect()
+    } else {
+        find(content)?
+    };
+    matches.retain(|m| m.0 < m.1);
+    matches.sort_unstable_by_key(|m| (m.0, m.1));
+
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    // Zero-based index of the line holding the byte `pos`
+    let line_of = |pos: usize| line_starts.partition_point(|start| *start <= pos) - 1;
+    let line_end = |line: usize| {
+        let end = line_starts
+            .get(line + 1)
+            .map_or(content.len(), |next| next - 1);
+        content[..end].strip_suffix('\r').map_or(end, str::len)
+    };
+
+    // Groups of matches sharing lines: first line, last line, matches
+    let mut groups: Vec<(usize, usize, Vec<BufferMatch<'b>>)> = Vec::new();
+    for m in matches {
+        let (first, last) = (line_of(m.0), line_of(m.1 - 1));
+        match groups.last_mut() {
+            Some(group) if first <= group.1 => {
+                group.1 = group.1.max(last);
+                group.2.push(m);
+            }
+            _ => groups.push((first, last, vec![m])),
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(first, last, matches)| {
+            let (start, end) = (line_starts[first], line_end(last));
+            let mut spans: Vec<(usize, usize)> = Vec::with_capacity(matches.len());
+            let mut patterns: Vec<&str> = Vec::new();
+            for (s, e, pattern) in matches {
+                // A match can end with the line break of its last line
+                let span = (s.max(start) - start, e.min(end).max(s) - start);
+                match spans.last_mut() {
+                    Some(last) if span.0 <= last.1 => last.1 = last.1.max(span.1),
+                    _ => spans.push(span),
+                }
+                if let Some(p) = pattern {
+                    if !patterns.contains(&p) {
+                        patterns.push(p);
+                    }
+                }
+            }
+            spans.retain(|(s, e)| s < e);
+            let block = &content[start..end];
             SearchResult {
-                line: parts,
+                line: highlight_spans(block, &spans),
                 word: "",
                 source,
-                idx: idx + 1,
+                idx: first + 1,
+                end_idx: last + 1,
+                patterns,
+                spans,
+                distances: Vec::new(),
             }
         })
-        .collect()
+        .collect())
+}
+
+fn regex_buffer_matches<'b>(
+    regex: &RegexPattern,
+    text: &str,
+) -> Result<Vec<BufferMatch<'b>>, PatternError> {
+    Ok(regex
+        .try_find_all(text)?
+        .into_iter()
+        .map(|m| (m.start, m.end, None))
+        .collect())
+}
+
+fn set_buffer_matches<'b>(
+    set: &'b regex::set::PatternSet,
+    text: &str,
+) -> Result<Vec<BufferMatch<'b>>, PatternError> {
+    let mut matches = Vec::new();
+    for i in set.try_matches(text)? {
+        let pattern = &set.patterns()[i];
+        let found = pattern.try_find_all(text)?;
+        matches.extend(
+            found
+                .into_iter()
+                .map(|m| (m.start, m.end, Some(pattern.get_pattern()))),
+        );
+    }
+    Ok(matches)
+}
+
+fn regex_hits<'b>(regex: &RegexPattern, line: &str) -> Result<Option<Hits<'b>>, PatternError> {
+    if !regex.try_is_match(line)? {
+        return Ok(None);
+    }
+    Ok(Some(Hits {
+        spans: match_spans(std::iter::once(regex), line)?,
+        ..Hits::default()
+    }))
+}
+
+fn fuzzy_hits<'b>(
+    fuzzy: &regex::fuzzy::FuzzyPattern,
+    boundary: regex::pattern::Boundary,
+    line: &str,
+) -> Option<Hits<'b>> {
+    let mut found = fuzzy.find_all(line);
+    found.retain(|m| boundary.accepts(line, m.start, m.end));
+    if found.is_empty() {
+        return None;
+    }
+    Some(Hits {
+        spans: found.iter().map(|m| (m.start, m.end)).collect(),
+        distances: found.iter().map(|m| m.distance).collect(),
+        ..Hits::default()
+    })
+}
+
+/// Returns the sorted and merged spans of the non-empty matches of the patterns
+pub(crate) fn match_spans<'p>(
+    patterns: impl Iterator<Item = &'p RegexPattern>,
+    line: &str,
+) -> Result<Vec<(usize, usize)>, PatternError> {
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for pattern in patterns {
+        let found = pattern.try_find_all(line)?;
+        spans.extend(
+            found
+                .iter()
+                .filter(|m| m.start < m.end)
+                .map(|m| (m.start, m.end)),
+        );
+    }
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    Ok(merged)
+}
+
+/// Searches the lines matching any pattern of the set
+///
+/// Each line is scanned a single time by the set, the matching patterns are reported in
+/// `SearchResult::patterns` and their matches are highlighted. The lines failing at runtime
+/// in the fancy engine are skipped.
+pub fn search_with_pattern_set<'a, 'b>(
+    set: &'b regex::set::PatternSet,
+    source: &'b str,
+    content: &'a str,
+) -> Vec<SearchResult<'a, 'b>> {
+    search_lines(source, content, false, |l| {
+        Ok(set_hits(set, l).unwrap_or(None))
+    })
+    .unwrap_or_default()
+}
+
+fn set_hits<'b>(
+    set: &'b regex::set::PatternSet,
+    line: &str,
+) -> Result<Option<Hits<'b>>, PatternError> {
+    let matched = set.try_matches(line)?;
+    if matched.is_empty() {
+        return Ok(None);
+    }
+    let patterns: Vec<&RegexPattern> = matched.iter().map(|i| &set.patterns()[*i]).collect();
+    let spans = match_spans(patterns.iter().copied(), line)?;
+    Ok(Some(Hits {
+        spans,
+        patterns: patterns.iter().map(|p| p.get_pattern()).collect(),
+        ..Hits::default()
+    }))
+}
+
+/// Splits the line at the spans, the spans being highlighted
+///
+/// The spans must be sorted and non-overlapping byte ranges of the line.
+pub fn highlight_spans<'a>(line: &'a str, spans: &[(usize, usize)]) -> Vec<(&'a str, &'a str)> {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for &(start, end) in spans {
+        if start > last {
+            parts.push((&line[last..start], color::config::Color::WHITE));
+        }
+        parts.push((&line[start..end], color::config::Color::BRIGHT_YELLOW));
+        last = end;
+    }
+    if last < line.len() {
+        parts.push((&line[last..], color::config::Color::WHITE));
+    }
+    parts
+}
+
+/// Searches the lines containing any of the literal patterns
+///
+/// Every hit is reported with its exact span in `SearchResult::spans`, the hits not
+/// respecting the boundary are dropped.
+pub fn search_fixed_strings<'a, 'b>(
+    fixed: &'b regex::fixed::FixedStrings,
+    boundary: regex::pattern::Boundary,
+    source: &'b str,
+    content: &'a str,
+) -> Vec<SearchResult<'a, 'b>> {
+    search_lines(source, content, false, |l| {
+        Ok(fixed_hits(fixed, boundary, l))
+    })
+    .unwrap_or_default()
+}
+
+fn fixed_hits<'b>(
+    fixed: &'b regex::fixed::FixedStrings,
+    boundary: regex::pattern::Boundary,
+    line: &str,
+) -> Option<Hits<'b>> {
+    let mut hits = fixed.find_all(line);
+    hits.retain(|m| boundary.accepts(line, m.start, m.end));
+    if hits.is_empty() {
+        return None;
+    }
+    let spans: Vec<(usize, usize)> = hits.iter().map(|m| (m.start, m.end)).collect();
+    let mut patterns: Vec<&str> = Vec::new();
+    for m in &hits {
+        let pattern = fixed.patterns()[m.pattern].as_str();
+        if !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+    Some(Hits {
+        spans,
+        patterns,
+        ..Hits::default()
+    })
 }
 
 mod utilities {
 
-    use crate::{glob::GlobPattern, Path};
+    use crate::{glob::GlobPattern, DrgrepError, Path};
     use std::{
         env,
-        error::Error,
         fs::{self, DirEntry},
         io::{self, stdin, Read},
         path::PathBuf,
@@ -492,15 +1343,32 @@ mod utilities {
     }
 
     impl GitIgnoreFiles {
-        pub fn load() -> Self {
+        /// Loads the rules of the `.gitignore` of the current directory, if any
+        ///
+        /// When the `.gitignore` can't be read, the error is returned along with the rules
+        /// ignoring the `.git` directory only.
+        pub fn load() -> (Self, Option<DrgrepError>) {
             let mut patterns = Vec::new();
             let mut entries = Vec::new();
+            let mut error = None;
             let cur_dir = if let Ok(p) = env::current_dir() {
                 p
             } else {
                 PathBuf::new()
             };
-            if let Ok(content) = fs::read_to_string(Path::new(".gitignore")) {
+            let gitignore = Path::new(".gitignore");
+            let content = match fs::read_to_string(gitignore) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(source) => {
+                    error = Some(DrgrepError::IgnoreFile {
+                        path: gitignore.to_path_buf(),
+                        source,
+                    });
+                    None
+                }
+            };
+            if let Some(content) = content {
                 // println!("gitignore content: \n {}", content);
                 content.lines().for_each(|l| {
                     patterns.push(Rc::new(GlobPattern::new(&format!(
@@ -519,10 +1387,11 @@ mod utilities {
             //     "format constructor for git: {}",
             //     &format!("{}/.git/**", cur_dir.display())
             // );
-            Self {
+            let ignore = Self {
                 pattern: patterns,
                 entries,
-            }
+            };
+            (ignore, error)
         }
 
         pub fn is_ignored(&self, p: &Path, current: &Path) -> bool {
@@ -537,26 +1406,75 @@ mod utilities {
         }
     }
 
-    pub fn can_read_to_utf8(path: &Path) -> Result<String, Box<dyn Error>> {
-        let mut file = fs::File::open(path)?;
+    pub fn can_read_to_utf8(path: &Path) -> Result<String, DrgrepError> {
         let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
-        Ok(String::from_utf8(buffer)?)
-    }
-
-    pub fn visit_dirs(dir: &Path, cb: &dyn Fn(&DirEntry)) -> io::Result<()> {
-        if dir.is_dir() {
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    visit_dirs(&path, cb)?;
-                } else {
-                    cb(&entry);
+        fs::File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut buffer))
+            .map_err(|e| DrgrepError::io(path, e))?;
+        String::from_utf8(buffer).map_err(|_| DrgrepError::Encoding {
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Calls `cb` for every file under `dir`, the unreadable entries being passed to `error`
+    pub fn visit_dirs(dir: &Path, cb: &dyn Fn(&DirEntry), error: &dyn Fn(DrgrepError)) {
+        if !dir.is_dir() {
+            return;
+        }
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => return error(DrgrepError::io(dir, e)),
+        };
+        for entry in entries {
+            match entry {
+                Ok(entry) if entry.path().is_dir() => visit_dirs(&entry.path(), cb, error),
+                Ok(entry) => cb(&entry),
+                Err(e) => error(DrgrepError::io(dir, e)),
+            }
+        }
+    }
+
+    /// Calls `cb` for every file under `root` that is not ignored by the `.gitignore` rules
+    ///
+    /// The directories and the entries that can't be read are passed to `error`, the walk
+    /// goes on with the other ones.
+    pub fn walk(
+        root: &Path,
+        ignore: &GitIgnoreFiles,
+        current_dir: &Path,
+        cb: &dyn Fn(&DirEntry),
+        error: &dyn Fn(DrgrepError),
+    ) {
+        let entries = match fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(e) => return error(DrgrepError::io(root, e)),
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    error(DrgrepError::io(root, e));
+                    continue;
                 }
+            };
+            let path = entry.path();
+            if ignore.is_ignored(&path, current_dir) {
+                continue;
+            }
+            if path.is_file() {
+                cb(&entry);
+            } else {
+                visit_dirs(
+                    &path,
+                    &|f: &DirEntry| {
+                        if !ignore.is_ignored(&f.path(), current_dir) {
+                            cb(f)
+                        }
+                    },
+                    error,
+                );
             }
         }
-        Ok(())
     }
 
     pub fn read_stdin() -> io::Result<String> {
@@ -564,6 +1482,27 @@ mod utilities {
         stdin().read_to_string(&mut buffer)?;
         Ok(buffer)
     }
+
+    /// Whether the standard input is piped or redirected from a file. Like ripgrep, a terminal
+    /// doesn't count, nor the `/dev/null` given to the background jobs and the cron jobs
+    pub fn stdin_is_piped() -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::{fd::AsFd, unix::fs::FileTypeExt};
+            let Ok(fd) = stdin().as_fd().try_clone_to_owned() else {
+                return false;
+            };
+            fs::File::from(fd).metadata().is_ok_and(|m| {
+                let file_type = m.file_type();
+                file_type.is_file() || file_type.is_fifo() || file_type.is_socket()
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            use std::io::IsTerminal;
+            !stdin().is_terminal()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -603,11 +1542,28 @@ C'est pas rustique.";
         let recherche = "Rust";
         let config = Config {
             search_content: None,
-            file_path: None,
+            paths: Vec::new(),
+            types: Vec::new(),
             search_key: Some(recherche),
             regex: None,
+            pattern_set: None,
+            fixed_strings: None,
+            key_pattern: None,
+            boundary: regex::pattern::Boundary::None,
+            ignore_accents: false,
+            fuzzy: None,
+            sort_by_distance: false,
+            multiline: false,
+            values: None,
+            unique: false,
+            count_values: false,
+            extract: None,
             sensitive: true,
-            path_is_dir: false,
+            replacement: None,
+            in_place: false,
+            write: false,
+            interactive: false,
+            no_messages: false,
         };
         let content = "\
 Rust:
@@ -625,11 +1581,28 @@ C'est pas rustique.";
         let recherche = "rUst";
         let config = Config {
             search_content: None,
-            file_path: None,
+            paths: Vec::new(),
+            types: Vec::new(),
             search_key: Some(recherche),
             regex: None,
+            pattern_set: None,
+            fixed_strings: None,
+            key_pattern: None,
+            boundary: regex::pattern::Boundary::None,
+            ignore_accents: false,
+            fuzzy: None,
+            sort_by_distance: false,
+            multiline: false,
+            values: None,
+            unique: false,
+            count_values: false,
+            extract: None,
             sensitive: true,
-            path_is_dir: false,
+            replacement: None,
+            in_place: false,
+            write: false,
+            interactive: false,
+            no_messages: false,
         };
         let content = "\
 Rust:
@@ -641,4 +1614,196 @@ C'est pas rustique.";
             search_word_insensitive_case(config.search_key.unwrap(), recherche, content)[0].line
         );
     }
+
+    #[test]
+    fn ignore_accents_search() {
+        let args = ArgParser::from(std::collections::HashMap::from([
+            ("k".to_string(), Some("securite".to_string())),
+            ("ignore-accents".to_string(), None),
+        ]));
+        let config = Config::new(&args).unwrap();
+        // NFC and NFD encoded accents
+        let content = "Rust:\nsécurité, rapidité.\nse\u{301}curite\u{301} d'abord";
+        let results = search_with_config(&config, "", content).unwrap();
+        assert_eq!(2, results.len());
+        assert_eq!(
+            vec![
+                ("sécurité", color::config::Color::BRIGHT_YELLOW),
+                (", rapidité.", color::config::Color::WHITE)
+            ],
+            results[0].line
+        );
+        assert_eq!(
+            (
+                "se\u{301}curite\u{301}",
+                color::config::Color::BRIGHT_YELLOW
+            ),
+            results[1].line[0]
+        );
+    }
+
+    #[test]
+    fn fuzzy_search_sorted_by_distance() {
+        let args = ArgParser::from(std::collections::HashMap::from([
+            ("k".to_string(), Some("connection".to_string())),
+            ("fuzzy".to_string(), Some("2".to_string())),
+            ("sort".to_string(), Some("distance".to_string())),
+        ]));
+        let config = Config::new(&args).unwrap();
+        let content = "lost the connexion\nopen a conection\nno match\nCONNECTION reset";
+        let results = search_with_config(&config, "", content).unwrap();
+        assert_eq!(
+            vec![(4, vec![0]), (2, vec![1]), (1, vec![2])],
+            results
+                .iter()
+                .map(|r| (r.idx, r.distances.clone()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            ("conection", color::config::Color::BRIGHT_YELLOW),
+            results[1].line[1]
+        );
+    }
+
+    #[test]
+    fn multiline_search() {
+        let args = ArgParser::from(std::collections::HashMap::from([
+            ("r".to_string(), Some(r"fn \w+\(\)\s*\{\s*\}".to_string())),
+            ("U".to_string(), None),
+        ]));
+        let config = Config::new(&args).unwrap();
+        let content = "fn a() {}\nfn empty() {\r\n\r\n}\nfn full() {\n    work();\n}\n";
+        let results = search_with_config(&config, "", content).unwrap();
+        assert_eq!(
+            vec![(1, 1), (2, 4)],
+            results
+                .iter()
+                .map(|r| (r.idx, r.end_idx))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![("fn empty() {\r\n\r\n}", color::config::Color::BRIGHT_YELLOW)],
+            results[1].line
+        );
+
+        // The anchors match at each line
+        let args = ArgParser::from(std::collections::HashMap::from([
+            ("r".to_string(), Some("^ERROR.*\\n.*timeout$".to_string())),
+            ("multiline".to_string(), None),
+        ]));
+        let config = Config::new(&args).unwrap();
+        let content = "ok\nERROR in job\n  caused by: timeout\nok";
+        let results = search_with_config(&config, "", content).unwrap();
+        assert_eq!(1, results.len());
+        assert_eq!((2, 3), (results[0].idx, results[0].end_idx));
+    }
+
+    #[test]
+    fn insensitive_key_is_compiled_once() {
+        let args = ArgParser::from(std::collections::HashMap::from([(
+            "k".to_string(),
+            Some("SÉcu".to_string()),
+        )]));
+        let config = Config::new(&args).unwrap();
+        assert!(config.key_pattern.is_some());
+        let results = search_with_config(&config, "", "no\nla sécurité").unwrap();
+        assert_eq!(vec![2], results.iter().map(|r| r.idx).collect::<Vec<_>>());
+
+        let args = ArgParser::from(std::collections::HashMap::from([
+            ("k".to_string(), Some("secu".to_string())),
+            ("s".to_string(), None),
+        ]));
+        assert!(Config::new(&args).unwrap().key_pattern.is_none());
+    }
+
+    #[test]
+    fn dotall_and_ascii_regexes() {
+        let args = ArgParser::from(std::collections::HashMap::from([
+            ("r".to_string(), Some("BEGIN.*END".to_string())),
+            ("U".to_string(), None),
+            ("multiline-dotall".to_string(), None),
+        ]));
+        let config = Config::new(&args).unwrap();
+        let results = search_with_config(&config, "", "BEGIN\nbody\nEND\n").unwrap();
+        assert_eq!((1, 3), (results[0].idx, results[0].end_idx));
+
+        let args = ArgParser::from(std::collections::HashMap::from([
+            ("r".to_string(), Some(r"^\w+$".to_string())),
+            ("no-unicode".to_string(), None),
+        ]));
+        let config = Config::new(&args).unwrap();
+        let results = search_with_config(&config, "", "plain\nsécurité").unwrap();
+        assert_eq!(vec![1], results.iter().map(|r| r.idx).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn report_lines_numbers_the_lines() {
+        let input: &[u8] = b"first\nb\xffd\n\nlast";
+        let mut lines = Vec::new();
+        report_lines(input, |line, offset| lines.push((offset, line.to_string()))).unwrap();
+        assert_eq!(
+            vec![
+                (0, "first\n".to_string()),
+                (1, "b\u{FFFD}d\n".to_string()),
+                (2, "\n".to_string()),
+                (3, "last".to_string()),
+            ],
+            lines
+        );
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn fancy_runtime_errors_are_returned() {
+        let args = ArgParser::from(std::collections::HashMap::from([
+            ("r".to_string(), Some(r"(a+)+\1b".to_string())),
+            ("engine".to_string(), Some("fancy".to_string())),
+        ]));
+        let config = Config::new(&args).unwrap();
+        let content = format!("ab\n{}", "a".repeat(40));
+        assert!(search_with_config(&config, "", &content).is_err());
+        // The public search skips the failing line
+        let results = search_with_regex(config.regex.as_ref().unwrap(), "", &content);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn walk_reports_errors_and_goes_on() -> std::io::Result<()> {
+        let dir = temp_dir::create_temp_dir()?;
+        fs::write(dir.path().join("a.txt"), "hello")?;
+        fs::create_dir(dir.path().join("sub"))?;
+        fs::write(dir.path().join("sub").join("b.bin"), [0xff, 0xfe])?;
+        let (ignore, _) = utilities::GitIgnoreFiles::load();
+        let files = RefCell::new(Vec::new());
+        let errors = RefCell::new(Vec::new());
+        utilities::walk(
+            dir.path(),
+            &ignore,
+            Path::new("/"),
+            &|f| {
+                files
+                    .borrow_mut()
+                    .push(utilities::can_read_to_utf8(&f.path()))
+            },
+            &|e| errors.borrow_mut().push(e),
+        );
+        let files = files.into_inner();
+        assert_eq!(2, files.len());
+        assert!(files.iter().any(|f| f.as_deref().ok() == Some("hello")));
+        assert!(files
+            .iter()
+            .any(|f| matches!(f, Err(DrgrepError::Encoding { .. }))));
+        assert!(errors.borrow().is_empty());
+
+        utilities::walk(
+            &dir.path().join("missing"),
+            &ignore,
+            Path::new("/"),
+            &|_| panic!("no file to visit"),
+            &|e| errors.borrow_mut().push(e),
+        );
+        let errors = errors.into_inner();
+        assert!(matches!(&errors[..], [DrgrepError::Io { path, .. }] if path.ends_with("missing")));
+        Ok(())
+    }
 }
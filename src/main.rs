@@ -1,9 +1,12 @@
-use std::process::exit;
+use std::{collections::HashMap, env, process::exit};
 
-use drgrep::{args::parser::ArgParser, run, Config, DEFAULT_MESSAGE};
+use drgrep::{
+    args::{generate, parser::ArgParser},
+    config_file, run, Config, DrgrepError, PROGRAM, SPEC,
+};
 
 fn main() {
-    let args: &mut ArgParser = &mut Default::default();
+    let args = &mut ArgParser::with_spec(&SPEC);
 
     if args.has("version") || args.has("v") {
         println!("{}", drgrep::VERSION);
@@ -11,42 +14,102 @@ fn main() {
     }
 
     if args.has("help") || args.has("h") {
-        println!("{}", drgrep::DEFAULT_MESSAGE);
+        print!("{}", drgrep::help());
         exit(0);
     }
 
-    // Catching the stdin if the user is using pipe
-    if args.has("content") {
-        match args.get("content") {
-            Some(content) => {
-                if content.as_str() == "@" {
-                    if let Ok(stdin_content) = drgrep::read_stdin() {
-                        args.set("content", stdin_content);
-                    }
-                }
+    if let Some(target) = args.get("generate") {
+        match target.parse() {
+            Ok(target) => print!("{}", generate::generate(&PROGRAM, target)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                exit(1);
             }
-            None => (),
         }
+        exit(0);
+    }
+
+    if args.has("undo-list") {
+        if let Err(e) = drgrep::replace::undo_list() {
+            eprintln!("An error occurred {}", e);
+            exit(1);
+        }
+        exit(0);
+    }
+
+    if args.has("undo") {
+        let id = args.get("undo").as_deref();
+        if let Err(e) = drgrep::replace::undo(id) {
+            eprintln!("An error occurred {}", e);
+            exit(1);
+        }
+        exit(0);
     }
-    if args.has("c") {
-        match args.get("c") {
-            Some(content) => {
-                if content.as_str() == "@" {
-                    if let Ok(stdin_content) = drgrep::read_stdin() {
-                        args.set("c", stdin_content);
-                    }
-                }
+
+    // The config files give the options the command line doesn't
+    let sources = if args.has("no-config") {
+        HashMap::new()
+    } else {
+        let current_dir = env::current_dir().unwrap_or_default();
+        match config_file::load_all(&current_dir) {
+            Ok(files) => config_file::apply(args, &files),
+            Err(e) => {
+                print_error(&e);
+                exit(1);
             }
-            None => (),
         }
+    };
+    if args.has("debug-config") {
+        print!("{}", config_file::debug(args, &sources));
+        exit(0);
     }
 
-    let config = Config::new(args).unwrap_or_else(|_: &str| {
-        println!("{}", DEFAULT_MESSAGE);
+    // Scripts relying on the unknown options being ignored can keep that behavior
+    let lenient = env::var("DRGREP_LENIENT").is_ok_and(|v| v == "1");
+    let mut config = Config::with_lenient(args, lenient).unwrap_or_else(|e| {
+        match e {
+            DrgrepError::Argument(_) => println!("{}", drgrep::help()),
+            // A usage error like grep, without the whole help
+            DrgrepError::UnknownOption(_) => {
+                print_error(&e);
+                eprintln!("For more information, try '--help'.");
+                exit(2);
+            }
+            _ => (),
+        }
+        print_error(&e);
         exit(1);
     });
-    if let Err(e) = run(config) {
-        eprintln!("An error occurred {}", e);
-        exit(1);
-    };
+    if ["c", "content"]
+        .iter()
+        .any(|k| args.get(k).as_deref() == Some("@"))
+    {
+        eprintln!("drgrep: warning: `-c @` is deprecated, use `-` to read the standard input");
+    }
+    // Like grep, the piped input is searched when no path is given
+    if config.paths.is_empty()
+        && config.search_content.is_none()
+        && !config.in_place
+        && drgrep::stdin_is_piped()
+    {
+        config.paths.push("-");
+    }
+    match run(config) {
+        Ok(()) => (),
+        // Like grep, the failed sources were reported while searching the others
+        Err(DrgrepError::Incomplete { .. }) => exit(2),
+        Err(e) => {
+            print_error(&e);
+            exit(1);
+        }
+    }
+}
+
+fn print_error(e: &DrgrepError) {
+    match e {
+        // The pattern errors are explained on their own
+        DrgrepError::Pattern(_) => eprintln!("{}", e),
+        DrgrepError::Argument(_) | DrgrepError::UnknownOption(_) => eprintln!("error: {}", e),
+        _ => eprintln!("drgrep: {}", e),
+    }
 }
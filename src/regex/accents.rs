@@ -0,0 +1,122 @@
+//! # Module Accents
+//!
+//! Accent-insensitive matching for natural-language text. The text is decomposed
+//! (Unicode NFD) and its combining marks are stripped, so `sécurité`, `securite` and a
+//! NFD-encoded `se\u{301}curite\u{301}` all become `securite`.
+//!
+//! The stripped text keeps the mapping to the bytes of the original text, so the matches
+//! found in it can be highlighted in the original.
+//!
+//! ```rust
+//! use drgrep::regex::accents::{strip_accents, Stripped};
+//!
+//! let line = "sécurité, rapidité";
+//! let stripped = Stripped::new(line);
+//! assert_eq!("securite, rapidite", stripped.as_str());
+//! assert_eq!((0, 10), stripped.original_span(0, 8));
+//! assert_eq!("securite", strip_accents("se\u{301}curite\u{301}"));
+//! ```
+//!
+//! The letters without decomposition, like `ø` or `æ`, are kept as they are.
+
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+
+/// Removes the accents and the other combining marks of the text
+pub fn strip_accents(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        decompose_canonical(c, |d| {
+            if !is_combining_mark(d) {
+                out.push(d);
+            }
+        });
+    }
+    out
+}
+
+/// A text without its accents, mapped to the original text
+#[derive(Debug, Clone)]
+pub struct Stripped<'a> {
+    original: &'a str,
+    text: String,
+    /// For each byte of `text`, the span of the original character it comes from
+    origins: Vec<(usize, usize)>,
+}
+
+impl<'a> Stripped<'a> {
+    /// Strips the accents of the original text
+    pub fn new(original: &'a str) -> Self {
+        let mut text = String::with_capacity(original.len());
+        let mut origins = Vec::with_capacity(original.len());
+        for (start, c) in original.char_indices() {
+            let end = start + c.len_utf8();
+            decompose_canonical(c, |d| {
+                if !is_combining_mark(d) {
+                    text.push(d);
+                    origins.extend(std::iter::repeat_n((start, end), d.len_utf8()));
+                }
+            });
+        }
+        Stripped {
+            original,
+            text,
+            origins,
+        }
+    }
+
+    /// Returns the text without accents
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Maps the span `start..end` of the stripped text to the original text
+    ///
+    /// The span is extended to the combining marks following it in the original, so the
+    /// accents of the matched letters are part of the span.
+    pub fn original_span(&self, start: usize, end: usize) -> (usize, usize) {
+        if start >= end {
+            let pos = self.origins.get(start).map_or(self.original.len(), |o| o.0);
+            return (pos, pos);
+        }
+        let mut original_end = self.origins[end - 1].1;
+        while let Some(c) = self.original[original_end..].chars().next() {
+            if !is_combining_mark(c) {
+                break;
+            }
+            original_end += c.len_utf8();
+        }
+        (self.origins[start].0, original_end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_accents() {
+        assert_eq!("securite", strip_accents("sécurité"));
+        assert_eq!("Ecole a Ile", strip_accents("École à Île"));
+        assert_eq!("naive", strip_accents("nai\u{308}ve"));
+        assert_eq!("søren", strip_accents("søren"));
+    }
+
+    #[test]
+    fn test_spans_map_to_nfc_text() {
+        let line = "la sécurité";
+        let stripped = Stripped::new(line);
+        let start = stripped.as_str().find("securite").unwrap();
+        let (s, e) = stripped.original_span(start, start + "securite".len());
+        assert_eq!("sécurité", &line[s..e]);
+    }
+
+    #[test]
+    fn test_spans_map_to_nfd_text() {
+        let line = "la se\u{301}curite\u{301}!";
+        let stripped = Stripped::new(line);
+        assert_eq!("la securite!", stripped.as_str());
+        let (s, e) = stripped.original_span(3, 11);
+        assert_eq!("se\u{301}curite\u{301}", &line[s..e]);
+        assert_eq!((s, s), stripped.original_span(3, 3));
+    }
+}
@@ -0,0 +1,197 @@
+//! # Module Cache
+//!
+//! A bounded cache of compiled patterns, shared between threads. When the cache is full,
+//! the least recently used pattern is dropped.
+//!
+//! The free functions of `regex::pattern` (`is_match`, `find`, ...) go through a global
+//! cache, so calling them in a loop compiles each pattern once. A `PatternCache` of its own
+//! controls the size and the lifetime of the compiled patterns:
+//!
+//! ```rust
+//! use drgrep::regex::cache::PatternCache;
+//!
+//! let cache = PatternCache::new(2);
+//! for line in ["id: 12", "id: 345"] {
+//!     let digits = cache.get(r"\d+").unwrap();
+//!     assert!(digits.is_match(line));
+//! }
+//! assert_eq!(1, cache.len());
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use super::pattern::{PatternError, RegexPattern};
+
+/// The capacity of the cache used by the free functions of `regex::pattern`
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// A compiled pattern and the time of its last use
+#[derive(Debug)]
+struct Entry {
+    pattern: Arc<RegexPattern>,
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    /// Incremented on every use
+    clock: u64,
+}
+
+/// A thread-safe LRU cache of compiled patterns
+#[derive(Debug)]
+pub struct PatternCache {
+    capacity: usize,
+    inner: Mutex<Entries>,
+}
+
+impl PatternCache {
+    /// Creates a cache keeping at most `capacity` patterns, 0 disables the caching
+    pub fn new(capacity: usize) -> Self {
+        PatternCache {
+            capacity,
+            inner: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Returns the cache used by the free functions of `regex::pattern`
+    pub fn global() -> &'static PatternCache {
+        static GLOBAL: OnceLock<PatternCache> = OnceLock::new();
+        GLOBAL.get_or_init(|| PatternCache::new(DEFAULT_CAPACITY))
+    }
+
+    // A panic while holding the lock can't leave the entries inconsistent
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the compiled pattern, compiling it on a miss
+    ///
+    /// The compilation errors aren't cached.
+    pub fn get(&self, pattern: &str) -> Result<Arc<RegexPattern>, PatternError> {
+        {
+            let mut inner = self.lock();
+            inner.clock += 1;
+            let clock = inner.clock;
+            if let Some(entry) = inner.entries.get_mut(pattern) {
+                entry.used = clock;
+                return Ok(Arc::clone(&entry.pattern));
+            }
+        }
+        // Compiled without the lock, so the other threads aren't blocked meanwhile
+        let compiled = Arc::new(RegexPattern::new(pattern)?);
+        if self.capacity == 0 {
+            return Ok(compiled);
+        }
+        let mut inner = self.lock();
+        if !inner.entries.contains_key(pattern) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+        inner.clock += 1;
+        let used = inner.clock;
+        let entry = inner.entries.entry(pattern.to_string()).or_insert(Entry {
+            pattern: compiled,
+            used,
+        });
+        entry.used = used;
+        Ok(Arc::clone(&entry.pattern))
+    }
+
+    /// Returns the maximum number of patterns kept
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of patterns kept
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Checks if no pattern is kept
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Checks if the pattern is kept, without counting it as a use
+    pub fn contains(&self, pattern: &str) -> bool {
+        self.lock().entries.contains_key(pattern)
+    }
+
+    /// Drops every pattern
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+}
+
+impl Default for PatternCache {
+    fn default() -> Self {
+        PatternCache::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reuses_compiled_patterns() {
+        let cache = PatternCache::new(4);
+        let first = cache.get(r"\d+").unwrap();
+        let second = cache.get(r"\d+").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(1, cache.len());
+
+        assert!(cache.get(r"(unclosed").is_err());
+        assert_eq!(1, cache.len());
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = PatternCache::new(2);
+        cache.get("a").unwrap();
+        cache.get("b").unwrap();
+        // "a" becomes the most recently used
+        cache.get("a").unwrap();
+        cache.get("c").unwrap();
+        assert_eq!(2, cache.len());
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("b"));
+        assert!(cache.contains("c"));
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let cache = PatternCache::new(0);
+        assert!(cache.get("a").unwrap().is_match("abc"));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let cache = Arc::new(PatternCache::new(8));
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let cache = Arc::clone(&cache);
+                std::thread::spawn(move || {
+                    let pattern = format!(r"x{}\d", i % 2);
+                    cache.get(&pattern).unwrap().is_match("x01 x12")
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+        assert_eq!(2, cache.len());
+    }
+}
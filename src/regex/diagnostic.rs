@@ -0,0 +1,421 @@
+//! # Module Diagnostic
+//!
+//! Explains why a pattern can't be compiled: the pattern is printed with a caret under the
+//! offending part, followed by a plain explanation and hints to fix it.
+//!
+//! ```rust
+//! use drgrep::regex::diagnostic::Diagnostic;
+//! use drgrep::regex::pattern::RegexPattern;
+//!
+//! let error = RegexPattern::new("foo(bar").unwrap_err();
+//! let diagnostic = Diagnostic::new("foo(bar", &error);
+//! assert_eq!(Some((3, 4)), diagnostic.span());
+//! assert_eq!(
+//!     "\
+//! error: unclosed group
+//!   foo(bar
+//!      ^
+//! the group opened here is never closed by a `)`
+//! hint: did you mean to escape `(`? `\\(` matches it literally
+//! hint: use -F (--fixed-strings) to search the text literally",
+//!     diagnostic.to_string()
+//! );
+//! ```
+
+use std::fmt;
+
+use regex_syntax::ast::ErrorKind;
+
+use super::pattern::PatternError;
+
+/// The diagnostic of a pattern that can't be compiled
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pattern: String,
+    /// Byte span of the offending part of the pattern
+    span: Option<(usize, usize)>,
+    message: String,
+    explanation: Option<String>,
+    hints: Vec<String>,
+}
+
+/// The hint escaping a character used as a regex operator
+fn escape_hint(c: char) -> String {
+    format!(
+        "did you mean to escape `{}`? `\\{}` matches it literally",
+        c, c
+    )
+}
+
+/// The hint of the syntax the default engine rejects
+fn engine_hint() -> String {
+    if cfg!(feature = "fancy") {
+        "use --engine fancy (or --engine auto) to compile it with fancy-regex".to_string()
+    } else if cfg!(feature = "pcre2") {
+        "use --engine pcre2 (or --engine auto) to compile it with PCRE2".to_string()
+    } else {
+        "drgrep must be built with the `fancy` or the `pcre2` feature to support it".to_string()
+    }
+}
+
+const LITERAL_HINT: &str = "use -F (--fixed-strings) to search the text literally";
+
+impl Diagnostic {
+    /// Explains the error raised by the compilation of the pattern
+    pub fn new(pattern: &str, error: &PatternError) -> Self {
+        let mut diagnostic = Diagnostic {
+            pattern: pattern.to_string(),
+            span: None,
+            message: error.to_string(),
+            explanation: None,
+            hints: Vec::new(),
+        };
+        match error {
+            PatternError::SizeLimitExceeded(limit) => {
+                diagnostic.message = format!(
+                    "the compiled regex exceeds the size limit of {} bytes",
+                    limit
+                );
+                diagnostic.explanation = Some(
+                    "large repetitions like `\\w{1000}` or big Unicode classes compile to huge \
+                     automata"
+                        .to_string(),
+                );
+                diagnostic
+                    .hints
+                    .push("raise the limit with --regex-size-limit <BYTES>".to_string());
+            }
+            #[cfg(feature = "fancy")]
+            PatternError::FancyError(e) => diagnostic.explain_fancy(e),
+            #[cfg(feature = "pcre2")]
+            PatternError::Pcre2Error(e) => diagnostic.explain_pcre2(e),
+            PatternError::Syntax { column, message } => {
+                diagnostic.message = message.clone();
+                let start = pattern
+                    .char_indices()
+                    .nth(column.saturating_sub(1))
+                    .map_or(pattern.len(), |(i, _)| i);
+                diagnostic.set_span(start, start);
+                diagnostic.hints.push(LITERAL_HINT.to_string());
+            }
+            PatternError::RegexError(e) => {
+                // The `regex` error is a preformatted text, the parser gives its structure
+                if !diagnostic.explain_syntax() {
+                    if let Some(line) = e.to_string().lines().last() {
+                        diagnostic.message = line.trim_start_matches("error: ").to_string();
+                    }
+                }
+            }
+            PatternError::Other(msg) => diagnostic.message = msg.clone(),
+        }
+        diagnostic
+    }
+
+    /// Returns the pattern
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the byte span of the offending part of the pattern, if known
+    pub fn span(&self) -> Option<(usize, usize)> {
+        self.span
+    }
+
+    /// Returns the short description of the error
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the hints to fix the pattern
+    pub fn hints(&self) -> &[String] {
+        &self.hints
+    }
+
+    /// Sets the span, at least one character long when the pattern goes that far
+    ///
+    /// The offsets out of the pattern or inside a character leave the span unknown.
+    fn set_span(&mut self, start: usize, end: usize) {
+        if !self.pattern.is_char_boundary(start)
+            || (end > start && !self.pattern.is_char_boundary(end))
+        {
+            self.span = None;
+            return;
+        }
+        let end = if end > start {
+            end
+        } else {
+            self.pattern[start..]
+                .chars()
+                .next()
+                .map_or(start, |c| start + c.len_utf8())
+        };
+        self.span = Some((start, end));
+    }
+
+    /// The character at the start of the span
+    fn spanned_char(&self) -> Option<char> {
+        self.span
+            .and_then(|(start, _)| self.pattern.get(start..))
+            .and_then(|rest| rest.chars().next())
+    }
+
+    /// Explains the syntax error found by the parser of the default engine
+    ///
+    /// Returns `false` when the parser accepts the pattern.
+    fn explain_syntax(&mut self) -> bool {
+        let error = match regex_syntax::ParserBuilder::new()
+            .build()
+            .parse(&self.pattern)
+        {
+            Ok(_) => return false,
+            Err(regex_syntax::Error::Parse(e)) => e,
+            Err(regex_syntax::Error::Translate(e)) => {
+                self.message = e.kind().to_string();
+                self.set_span(e.span().start.offset, e.span().end.offset);
+                return true;
+            }
+            Err(_) => return false,
+        };
+        self.message = error.kind().to_string();
+        self.set_span(error.span().start.offset, error.span().end.offset);
+        let spanned = self.spanned_char();
+        let (explanation, hint) = match error.kind() {
+            ErrorKind::GroupUnclosed => (
+                Some("the group opened here is never closed by a `)`"),
+                Some(escape_hint('(')),
+            ),
+            ErrorKind::GroupUnopened => (
+                Some("this `)` doesn't close any group"),
+                Some(escape_hint(')')),
+            ),
+            ErrorKind::ClassUnclosed => (
+                Some("the character class opened here is never closed by a `]`"),
+                Some(escape_hint('[')),
+            ),
+            ErrorKind::RepetitionMissing => (
+                Some("a repetition applies to the item before it, and there is none here"),
+                spanned.map(escape_hint),
+            ),
+            ErrorKind::RepetitionCountUnclosed | ErrorKind::RepetitionCountDecimalEmpty => (
+                Some("a counted repetition is written `{n}`, `{n,}` or `{n,m}`"),
+                Some(escape_hint('{')),
+            ),
+            ErrorKind::RepetitionCountInvalid => (
+                Some("the minimum of a counted repetition `{n,m}` can't exceed its maximum"),
+                None,
+            ),
+            ErrorKind::EscapeUnrecognized => (
+                Some("this escape sequence has no meaning in a regex"),
+                Some("`\\\\` matches a literal backslash".to_string()),
+            ),
+            ErrorKind::EscapeUnexpectedEof => (
+                Some("the pattern ends with a lone backslash"),
+                Some("`\\\\` matches a literal backslash".to_string()),
+            ),
+            ErrorKind::UnsupportedLookAround => (
+                Some("the default engine doesn't support the lookarounds"),
+                Some(engine_hint()),
+            ),
+            ErrorKind::UnsupportedBackreference => (
+                Some("the default engine doesn't support the backreferences"),
+                Some(engine_hint()),
+            ),
+            _ => (None, None),
+        };
+        self.explanation = explanation.map(str::to_string);
+        self.hints.extend(hint);
+        if !matches!(
+            error.kind(),
+            ErrorKind::UnsupportedLookAround | ErrorKind::UnsupportedBackreference
+        ) {
+            self.hints.push(LITERAL_HINT.to_string());
+        }
+        true
+    }
+
+    /// Explains the error of the fancy engine
+    #[cfg(feature = "fancy")]
+    fn explain_fancy(&mut self, error: &fancy_regex::Error) {
+        use fancy_regex::{CompileError, Error, ParseError};
+
+        match error {
+            Error::ParseError(_, kind) => {
+                self.message = kind.to_string();
+                // The position is in the compiled pattern, which differs from the pattern
+                // given with -w, -x or --ignore-accents: the error is located in the latter
+                // when it fails the same way
+                if let Err(Error::ParseError(position, own)) =
+                    fancy_regex::Regex::new(&self.pattern)
+                {
+                    if own.to_string() == self.message {
+                        self.set_span(position, position);
+                    }
+                }
+                match kind {
+                    ParseError::UnclosedOpenParen => self.hints.push(escape_hint('(')),
+                    ParseError::TargetNotRepeatable | ParseError::InvalidRepeat => {
+                        if let Some(c) = self.spanned_char() {
+                            self.hints.push(escape_hint(c));
+                        }
+                    }
+                    _ => (),
+                }
+                self.hints.push(LITERAL_HINT.to_string());
+            }
+            Error::CompileError(CompileError::LookBehindNotConst) => {
+                self.message = "the lookbehind doesn't have a fixed length".to_string();
+                self.explanation = Some(
+                    "the lookbehinds can only match a fixed number of characters, like \
+                     `(?<=ab)` but not `(?<=a+)`"
+                        .to_string(),
+                );
+            }
+            Error::CompileError(CompileError::InvalidBackref(group)) => {
+                self.message = format!("the backreference refers to a missing group {}", group);
+                self.explanation =
+                    Some("a backreference `\\N` repeats the text of the group N".to_string());
+            }
+            e => self.message = e.to_string(),
+        }
+    }
+
+    #[cfg(feature = "pcre2")]
+    fn explain_pcre2(&mut self, error: &pcre2::Error) {
+        // The message of the library follows the kind of the error and its offset
+        let text = error.to_string();
+        self.message = text.splitn(3, ": ").nth(2).unwrap_or(&text).to_string();
+        if !matches!(error.kind(), pcre2::ErrorKind::Compile) {
+            return;
+        }
+        // Like with the fancy engine, the error is located in the pattern given when it
+        // fails the same way
+        if let Err(own) = pcre2::bytes::RegexBuilder::new()
+            .utf(true)
+            .build(&self.pattern)
+        {
+            if let (true, Some(offset)) = (own.code() == error.code(), own.offset()) {
+                self.set_span(offset, offset);
+            }
+        }
+        self.hints.push(LITERAL_HINT.to_string());
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error: {}", self.message)?;
+        if let Some((start, end)) = self.span {
+            let offset = self.pattern[..start].chars().count();
+            let width = self.pattern[start..end].chars().count().max(1);
+            write!(f, "\n  {}", self.pattern)?;
+            write!(f, "\n  {}{}", " ".repeat(offset), "^".repeat(width))?;
+        } else {
+            write!(f, "\n  {}", self.pattern)?;
+        }
+        if let Some(explanation) = &self.explanation {
+            write!(f, "\n{}", explanation)?;
+        }
+        for hint in &self.hints {
+            write!(f, "\nhint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::pattern::RegexPattern;
+
+    fn diagnose(pattern: &str) -> Diagnostic {
+        Diagnostic::new(pattern, &RegexPattern::new(pattern).unwrap_err())
+    }
+
+    #[test]
+    fn test_repetition_missing() {
+        let diagnostic = diagnose("*invalid");
+        assert_eq!(Some((0, 1)), diagnostic.span());
+        assert_eq!(
+            "repetition operator missing expression",
+            diagnostic.message()
+        );
+        assert_eq!(
+            vec![escape_hint('*'), LITERAL_HINT.to_string()],
+            diagnostic.hints()
+        );
+        assert!(diagnostic.to_string().contains("\n  *invalid\n  ^\n"));
+    }
+
+    #[test]
+    fn test_caret_counts_characters() {
+        let diagnostic = diagnose("été [abc");
+        assert_eq!(
+            "error: unclosed character class",
+            diagnostic.to_string().lines().next().unwrap()
+        );
+        assert!(diagnostic.to_string().contains("\n  été [abc\n      ^"));
+        assert_eq!(escape_hint('['), diagnostic.hints()[0]);
+    }
+
+    #[test]
+    fn test_unsupported_syntax() {
+        let diagnostic = diagnose("foo(?=bar)");
+        assert_eq!(vec![engine_hint()], diagnostic.hints());
+        let diagnostic = diagnose(r"\p{Greek");
+        assert!(diagnostic.span().is_some());
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let error = RegexPattern::builder(r"\w{1000}")
+            .size_limit(1024)
+            .build()
+            .unwrap_err();
+        let diagnostic = Diagnostic::new(r"\w{1000}", &error);
+        assert_eq!(None, diagnostic.span());
+        assert!(diagnostic.hints()[0].contains("--regex-size-limit"));
+    }
+
+    #[test]
+    fn test_posix_syntax_error() {
+        let error =
+            crate::regex::posix::translate("a[[:alfa:]]", crate::regex::posix::Syntax::Basic)
+                .unwrap_err();
+        let diagnostic = Diagnostic::new("a[[:alfa:]]", &error);
+        assert_eq!(Some((2, 3)), diagnostic.span());
+        assert_eq!("unknown character class [:alfa:]", diagnostic.message());
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn test_fancy_errors() {
+        use crate::regex::pattern::Engine;
+
+        let pattern = r"(?<=a+)b";
+        let error = RegexPattern::with_engine(pattern, Engine::Fancy).unwrap_err();
+        let diagnostic = Diagnostic::new(pattern, &error);
+        assert_eq!(
+            "the lookbehind doesn't have a fixed length",
+            diagnostic.message()
+        );
+
+        // The compiled pattern is longer than the one given
+        let error = RegexPattern::with_engine(r"(?:ab))$", Engine::Fancy).unwrap_err();
+        let diagnostic = Diagnostic::new("ab)", &error);
+        assert_eq!(Some((2, 3)), diagnostic.span());
+        let diagnostic = Diagnostic::new("é", &error);
+        assert_eq!(None, diagnostic.span());
+    }
+
+    #[cfg(feature = "pcre2")]
+    #[test]
+    fn test_pcre2_errors() {
+        use crate::regex::pattern::Engine;
+
+        let error = RegexPattern::with_engine("(a", Engine::Pcre2).unwrap_err();
+        let diagnostic = Diagnostic::new("(a", &error);
+        assert_eq!("missing closing parenthesis", diagnostic.message());
+        assert_eq!(Some((2, 2)), diagnostic.span());
+        assert_eq!(vec![LITERAL_HINT], diagnostic.hints());
+    }
+}
@@ -0,0 +1,188 @@
+//! # Module Fixed
+//!
+//! Fixed-string search: any number of literal patterns are compiled into a single
+//! Aho-Corasick automaton, so a line is scanned once whether there are two or
+//! thousands of patterns (a list of leaked tokens, deprecated symbol names...).
+//!
+//! The case-insensitive matching folds the ASCII letters in the automaton. When a pattern has
+//! non-ASCII characters, the patterns are matched by a case-insensitive regex of their
+//! alternation instead, with the Unicode simple case folding of the regex engine like the
+//! key search.
+//!
+//! ```rust
+//! use drgrep::regex::fixed::FixedStrings;
+//!
+//! let fixed = FixedStrings::new(["token_a", "TOKEN_B"], true).unwrap();
+//! let hits = fixed.find_all("use token_b then Token_A");
+//! assert_eq!(2, hits.len());
+//! assert_eq!((1, 4, 11), (hits[0].pattern, hits[0].start, hits[0].end));
+//! assert_eq!((0, 17, 24), (hits[1].pattern, hits[1].start, hits[1].end));
+//! ```
+
+use aho_corasick::{AhoCorasick, MatchKind};
+
+use super::pattern::PatternError;
+
+/// A hit of a literal pattern
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedMatch {
+    /// Index of the pattern which matched
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A set of literal patterns matched together
+#[derive(Debug, Clone)]
+pub struct FixedStrings {
+    matcher: Matcher,
+    patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Automaton(AhoCorasick),
+    /// The alternation of the patterns, the longest first, each one in a group. `order` is the
+    /// index of the pattern of each group
+    Regex {
+        regex: ::regex::Regex,
+        order: Vec<usize>,
+    },
+}
+
+impl FixedStrings {
+    /// Compiles the literal patterns, ignoring the case when `case_insensitive` is set
+    pub fn new<I, S>(patterns: I, case_insensitive: bool) -> Result<Self, PatternError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns: Vec<String> = patterns
+            .into_iter()
+            .map(|p| p.as_ref().to_string())
+            .collect();
+        let matcher = if case_insensitive && patterns.iter().any(|p| !p.is_ascii()) {
+            // The longest pattern wins at a position, like in the automaton
+            let mut order: Vec<usize> = (0..patterns.len()).collect();
+            order.sort_by_key(|&i| std::cmp::Reverse(patterns[i].len()));
+            let alternation: Vec<String> = order
+                .iter()
+                .map(|&i| format!("({})", ::regex::escape(&patterns[i])))
+                .collect();
+            let regex = ::regex::RegexBuilder::new(&alternation.join("|"))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| PatternError::Other(e.to_string()))?;
+            Matcher::Regex { regex, order }
+        } else {
+            let automaton = AhoCorasick::builder()
+                .ascii_case_insensitive(case_insensitive)
+                .match_kind(MatchKind::LeftmostLongest)
+                .build(&patterns)
+                .map_err(|e| PatternError::Other(e.to_string()))?;
+            Matcher::Automaton(automaton)
+        };
+        Ok(FixedStrings { matcher, patterns })
+    }
+
+    /// Returns the literal patterns
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Checks if any pattern occurs in the text
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.matcher {
+            Matcher::Automaton(automaton) => automaton.is_match(text),
+            Matcher::Regex { regex, .. } => regex.is_match(text),
+        }
+    }
+
+    /// Finds every non-overlapping hit in the text, preferring the longest pattern at a position
+    pub fn find_all(&self, text: &str) -> Vec<FixedMatch> {
+        match &self.matcher {
+            Matcher::Automaton(automaton) => automaton
+                .find_iter(text)
+                .map(|m| FixedMatch {
+                    pattern: m.pattern().as_usize(),
+                    start: m.start(),
+                    end: m.end(),
+                })
+                .collect(),
+            Matcher::Regex { regex, order } => regex
+                .captures_iter(text)
+                .filter_map(|captures| {
+                    let (group, m) = captures
+                        .iter()
+                        .enumerate()
+                        .skip(1)
+                        .find_map(|(group, m)| Some((group, m?)))?;
+                    Some(FixedMatch {
+                        pattern: order[group - 1],
+                        start: m.start(),
+                        end: m.end(),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_sensitive() {
+        let fixed = FixedStrings::new(["foo", "bar"], false).unwrap();
+        assert!(fixed.is_match("a bar"));
+        assert!(!fixed.is_match("FOO"));
+        assert_eq!(
+            vec![
+                FixedMatch {
+                    pattern: 0,
+                    start: 0,
+                    end: 3
+                },
+                FixedMatch {
+                    pattern: 1,
+                    start: 4,
+                    end: 7
+                }
+            ],
+            fixed.find_all("foo bar")
+        );
+    }
+
+    #[test]
+    fn test_longest_match_wins() {
+        let fixed = FixedStrings::new(["dep", "deprecated_fn"], false).unwrap();
+        let hits = fixed.find_all("call deprecated_fn()");
+        assert_eq!(1, hits.len());
+        assert_eq!(1, hits[0].pattern);
+        assert_eq!((5, 18), (hits[0].start, hits[0].end));
+    }
+
+    #[test]
+    fn test_many_patterns() {
+        let tokens: Vec<String> = (0..5000).map(|i| format!("tok{:05}", i)).collect();
+        let fixed = FixedStrings::new(&tokens, true).unwrap();
+        assert_eq!(4242, fixed.find_all("leaked TOK04242 here")[0].pattern);
+        assert!(!fixed.is_match("tok5000x"));
+    }
+
+    #[test]
+    fn test_unicode_case_folding() {
+        let fixed = FixedStrings::new(["SÉCURITÉ", "é", "éa"], true).unwrap();
+        assert!(fixed.is_match("la sécurité"));
+        let hits: Vec<(usize, usize, usize)> = fixed
+            .find_all("LA SÉCURITÉ ÉA")
+            .iter()
+            .map(|m| (m.pattern, m.start, m.end))
+            .collect();
+        assert_eq!(vec![(0, 3, 13), (2, 14, 17)], hits);
+        assert!(!FixedStrings::new(["sécurité"], false)
+            .unwrap()
+            .is_match("SÉCURITÉ"));
+    }
+}
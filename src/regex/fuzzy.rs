@@ -0,0 +1,227 @@
+//! # Module Fuzzy
+//!
+//! Approximate matching, like `agrep -N`: a key matches any substring of the text within a
+//! Levenshtein distance (insertions, deletions and substitutions of characters).
+//!
+//! The substrings are found with the Sellers algorithm, a dynamic programming over the
+//! characters of the key and of the text, in `O(key × text)` per text.
+//!
+//! ```rust
+//! use drgrep::regex::fuzzy::FuzzyPattern;
+//!
+//! let fuzzy = FuzzyPattern::new("receive", 1, false).unwrap();
+//! let hits = fuzzy.find_all("we recive the receve, not the reply");
+//! assert_eq!(2, hits.len());
+//! assert_eq!((3, 9, 1), (hits[0].start, hits[0].end, hits[0].distance));
+//! assert_eq!((14, 20, 1), (hits[1].start, hits[1].end, hits[1].distance));
+//! ```
+
+use super::pattern::PatternError;
+
+/// A substring of the text close to the key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuzzyMatch {
+    pub start: usize,
+    pub end: usize,
+    /// Edit distance between the substring and the key
+    pub distance: usize,
+}
+
+/// A key matched approximately
+#[derive(Debug, Clone)]
+pub struct FuzzyPattern {
+    key: String,
+    chars: Vec<char>,
+    max_distance: usize,
+    case_insensitive: bool,
+}
+
+/// A cell of the dynamic programming: the best distance and where its alignment starts
+#[derive(Clone, Copy)]
+struct Cell {
+    distance: usize,
+    start: usize,
+}
+
+impl FuzzyPattern {
+    /// Creates a pattern matching the substrings within `max_distance` edits of the key
+    ///
+    /// The distance must be lower than the length of the key, the empty substring would
+    /// match everywhere otherwise.
+    pub fn new(
+        key: &str,
+        max_distance: usize,
+        case_insensitive: bool,
+    ) -> Result<Self, PatternError> {
+        let chars: Vec<char> = key.chars().collect();
+        if max_distance >= chars.len() {
+            return Err(PatternError::Other(format!(
+                "the fuzzy distance {} must be lower than the length of '{}'",
+                max_distance, key
+            )));
+        }
+        Ok(FuzzyPattern {
+            key: key.to_string(),
+            chars,
+            max_distance,
+            case_insensitive,
+        })
+    }
+
+    /// Returns the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the maximum edit distance
+    pub fn max_distance(&self) -> usize {
+        self.max_distance
+    }
+
+    fn same(&self, a: char, b: char) -> bool {
+        a == b || (self.case_insensitive && a.to_lowercase().eq(b.to_lowercase()))
+    }
+
+    /// Checks if a substring of the text is close enough to the key
+    pub fn is_match(&self, text: &str) -> bool {
+        !self.find_all(text).is_empty()
+    }
+
+    /// Finds the non-overlapping substrings close to the key, with their byte spans
+    ///
+    /// Among the overlapping candidates, the substring with the lowest distance is kept,
+    /// the shortest one on a tie.
+    pub fn find_all(&self, text: &str) -> Vec<FuzzyMatch> {
+        let m = self.chars.len();
+        // Byte offset of each character, plus the end of the text
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        let mut column: Vec<Cell> = (0..=m)
+            .map(|i| Cell {
+                distance: i,
+                start: 0,
+            })
+            .collect();
+        let mut matches = Vec::new();
+        // Best candidate of the current run of matching ends
+        let mut best: Option<FuzzyMatch> = None;
+        let mut last_end = 0;
+
+        for (j, t) in text.chars().enumerate() {
+            let mut next = Vec::with_capacity(m + 1);
+            next.push(Cell {
+                distance: 0,
+                start: j + 1,
+            });
+            for i in 1..=m {
+                let cost = if self.same(self.chars[i - 1], t) {
+                    0
+                } else {
+                    1
+                };
+                let diagonal = Cell {
+                    distance: column[i - 1].distance + cost,
+                    start: column[i - 1].start,
+                };
+                let deletion = Cell {
+                    distance: next[i - 1].distance + 1,
+                    start: next[i - 1].start,
+                };
+                let insertion = Cell {
+                    distance: column[i].distance + 1,
+                    start: column[i].start,
+                };
+                let cell = [deletion, insertion].into_iter().fold(diagonal, |a, b| {
+                    if b.distance < a.distance {
+                        b
+                    } else {
+                        a
+                    }
+                });
+                next.push(cell);
+            }
+            column = next;
+
+            let cell = column[m];
+            if cell.distance <= self.max_distance && cell.start <= j {
+                let candidate = FuzzyMatch {
+                    start: cell.start,
+                    end: j + 1,
+                    distance: cell.distance,
+                };
+                if best.is_none_or(|b| candidate.distance < b.distance) {
+                    best = Some(candidate);
+                }
+            } else if let Some(b) = best.take() {
+                if b.start >= last_end {
+                    last_end = b.end;
+                    matches.push(b);
+                }
+            }
+        }
+        if let Some(b) = best {
+            if b.start >= last_end {
+                matches.push(b);
+            }
+        }
+
+        matches
+            .into_iter()
+            .map(|f| FuzzyMatch {
+                start: offsets[f.start],
+                end: offsets[f.end],
+                distance: f.distance,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'t>(fuzzy: &FuzzyPattern, text: &'t str) -> Vec<(&'t str, usize)> {
+        fuzzy
+            .find_all(text)
+            .iter()
+            .map(|f| (&text[f.start..f.end], f.distance))
+            .collect()
+    }
+
+    #[test]
+    fn test_exact_and_typos() {
+        let fuzzy = FuzzyPattern::new("connection", 2, false).unwrap();
+        assert_eq!(vec![("connection", 0)], texts(&fuzzy, "lost connection"));
+        assert_eq!(vec![("conection", 1)], texts(&fuzzy, "lost conection!"));
+        assert_eq!(vec![("connexion", 2)], texts(&fuzzy, "la connexion"));
+        assert!(!fuzzy.is_match("a cold night"));
+        // Two substitutions away
+        assert!(fuzzy.is_match("collection"));
+    }
+
+    #[test]
+    fn test_case_and_unicode() {
+        let fuzzy = FuzzyPattern::new("sécurité", 1, true).unwrap();
+        assert_eq!(vec![("SECURITÉ", 1)], texts(&fuzzy, "la SECURITÉ"));
+        let fuzzy = FuzzyPattern::new("sécurité", 1, false).unwrap();
+        assert!(!fuzzy.is_match("la SECURITÉ"));
+    }
+
+    #[test]
+    fn test_several_matches() {
+        let fuzzy = FuzzyPattern::new("error", 1, false).unwrap();
+        assert_eq!(
+            vec![("eror", 1), ("error", 0)],
+            texts(&fuzzy, "eror then error")
+        );
+    }
+
+    #[test]
+    fn test_distance_too_large() {
+        assert!(FuzzyPattern::new("ab", 2, false).is_err());
+        assert!(FuzzyPattern::new("", 0, false).is_err());
+    }
+}
@@ -1 +1,8 @@
-pub mod pattern;
\ No newline at end of file
+pub mod accents;
+pub mod cache;
+pub mod diagnostic;
+pub mod fixed;
+pub mod fuzzy;
+pub mod pattern;
+pub mod posix;
+pub mod set;
@@ -6,16 +6,47 @@
 //! Supported patterns:
 //! - All standard regular expression syntax supported by the `regex` crate
 //! - Capture groups for more advanced replacement scenarios
+//! - Lookarounds and backreferences with the `fancy` engine (cargo feature `fancy`, enabled
+//!   by default), see [`Engine`]
+//! - The Perl syntax of PCRE2, like the possessive quantifiers and the recursion, with the
+//!   `pcre2` engine (cargo feature `pcre2`)
+//!
+//! ```rust
+//! # #[cfg(feature = "fancy")]
+//! # {
+//! use drgrep::regex::pattern::{Engine, RegexPattern};
+//!
+//! // Doubled words
+//! assert!(RegexPattern::new(r"(\w+)\s+\1").is_err());
+//! let pattern = RegexPattern::with_engine(r"(\w+)\s+\1", Engine::Auto).unwrap();
+//! assert_eq!(Engine::Fancy, pattern.engine());
+//! assert_eq!("the the", pattern.find("see the the cat").unwrap().text);
+//! # }
+//! ```
 
-use regex::Regex;
+use super::cache::PatternCache;
+use regex::{Regex, RegexSet};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
+use std::ops::Index;
+use std::str::FromStr;
 
 /// Errors specific to pattern matching operations
 #[derive(Debug)]
 pub enum PatternError {
     /// Error in the regular expression pattern
     RegexError(regex::Error),
+    /// The compiled pattern exceeds the size limit, in bytes
+    SizeLimitExceeded(usize),
+    /// Error in the pattern compiled by the fancy engine
+    #[cfg(feature = "fancy")]
+    FancyError(Box<fancy_regex::Error>),
+    /// Error in the pattern compiled by the pcre2 engine, or raised while matching
+    #[cfg(feature = "pcre2")]
+    Pcre2Error(pcre2::Error),
+    /// Error in a pattern translated to the regex syntax, at a 1-based column
+    Syntax { column: usize, message: String },
     /// Other errors
     Other(String),
 }
@@ -24,6 +55,18 @@ impl fmt::Display for PatternError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             PatternError::RegexError(e) => write!(f, "Regex error: {}", e),
+            #[cfg(feature = "fancy")]
+            PatternError::FancyError(e) => write!(f, "Regex error: {}", e),
+            #[cfg(feature = "pcre2")]
+            PatternError::Pcre2Error(e) => write!(f, "Regex error: {}", e),
+            PatternError::SizeLimitExceeded(limit) => write!(
+                f,
+                "Regex error: the compiled pattern exceeds the size limit of {} bytes",
+                limit
+            ),
+            PatternError::Syntax { column, message } => {
+                write!(f, "Syntax error at column {}: {}", column, message)
+            }
             PatternError::Other(msg) => write!(f, "Error: {}", msg),
         }
     }
@@ -33,14 +76,94 @@ impl Error for PatternError {}
 
 impl From<regex::Error> for PatternError {
     fn from(err: regex::Error) -> PatternError {
-        PatternError::RegexError(err)
+        match err {
+            regex::Error::CompiledTooBig(limit) => PatternError::SizeLimitExceeded(limit),
+            err => PatternError::RegexError(err),
+        }
+    }
+}
+
+#[cfg(feature = "fancy")]
+impl From<fancy_regex::Error> for PatternError {
+    fn from(err: fancy_regex::Error) -> PatternError {
+        match &err {
+            fancy_regex::Error::CompileError(fancy_regex::CompileError::InnerError(inner)) => {
+                match inner.size_limit() {
+                    Some(limit) => PatternError::SizeLimitExceeded(limit),
+                    None => PatternError::FancyError(Box::new(err)),
+                }
+            }
+            _ => PatternError::FancyError(Box::new(err)),
+        }
+    }
+}
+
+#[cfg(feature = "pcre2")]
+impl From<pcre2::Error> for PatternError {
+    fn from(err: pcre2::Error) -> PatternError {
+        PatternError::Pcre2Error(err)
+    }
+}
+
+/// The regex engine compiling the patterns
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Engine {
+    /// The `regex` crate: linear time, no lookarounds nor backreferences
+    #[default]
+    Default,
+    /// The `fancy-regex` crate: backtracking for the lookarounds and the backreferences,
+    /// requires the `fancy` cargo feature
+    Fancy,
+    /// The PCRE2 library: backtracking with the Perl syntax, like the possessive quantifiers,
+    /// the atomic groups and the recursion, requires the `pcre2` cargo feature
+    Pcre2,
+    /// The default engine, falling back to the richer ones, fancy then pcre2, when it can't
+    /// compile the pattern
+    Auto,
+}
+
+impl Engine {
+    /// Checks if the engine can be used in this build
+    pub fn is_available(&self) -> bool {
+        match self {
+            Engine::Default | Engine::Auto => true,
+            Engine::Fancy => cfg!(feature = "fancy"),
+            Engine::Pcre2 => cfg!(feature = "pcre2"),
+        }
     }
 }
 
+impl FromStr for Engine {
+    type Err = PatternError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "default" => Ok(Engine::Default),
+            "fancy" => Ok(Engine::Fancy),
+            "pcre2" => Ok(Engine::Pcre2),
+            "auto" => Ok(Engine::Auto),
+            _ => Err(PatternError::Other(format!(
+                "unknown regex engine '{}'",
+                name
+            ))),
+        }
+    }
+}
+
+/// A pattern compiled by one of the engines
+#[derive(Debug)]
+enum Compiled {
+    Default(Regex),
+    #[cfg(feature = "fancy")]
+    Fancy(fancy_regex::Regex),
+    #[cfg(feature = "pcre2")]
+    Pcre2(pcre2::bytes::Regex),
+}
+
 /// Main structure for pattern matching and replacement
 #[derive(Debug)]
 pub struct RegexPattern {
-    regex: Regex,
+    regex: Compiled,
     pattern: String,
 }
 
@@ -52,11 +175,582 @@ pub struct Match {
     pub end: usize,
 }
 
+/// How the matches of a pattern must be delimited
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Boundary {
+    /// The pattern can match anywhere
+    #[default]
+    None,
+    /// The match must be a whole word: neither preceded nor followed by a word character
+    Word,
+    /// The match must be the whole line
+    Line,
+}
+
+impl Boundary {
+    /// Wraps the pattern in Unicode word boundaries or full-line anchors
+    ///
+    /// The half word boundaries let a pattern starting or ending with a non-word character,
+    /// like `-v`, match as a whole word too.
+    ///
+    /// ```
+    /// use drgrep::regex::pattern::{Boundary, RegexPattern};
+    ///
+    /// let pattern = RegexPattern::new(&Boundary::Word.wrap("id")).unwrap();
+    /// assert!(pattern.is_match("the id, the name"));
+    /// assert!(!pattern.is_match("width valid idle"));
+    /// ```
+    pub fn wrap(&self, pattern: &str) -> String {
+        match self {
+            Boundary::None => pattern.to_string(),
+            Boundary::Word => format!(r"\b{{start-half}}(?:{})\b{{end-half}}", pattern),
+            Boundary::Line => format!("^(?:{})$", pattern),
+        }
+    }
+
+    /// Checks that the match `start..end` of `text` respects the boundary
+    ///
+    /// Used for the matchers which can't wrap their patterns, like the fixed strings.
+    pub fn accepts(&self, text: &str, start: usize, end: usize) -> bool {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        match self {
+            Boundary::None => true,
+            Boundary::Word => {
+                !text[..start].chars().next_back().is_some_and(is_word)
+                    && !text[end..].chars().next().is_some_and(is_word)
+            }
+            Boundary::Line => start == 0 && end == text.len(),
+        }
+    }
+}
+
+/// The capture groups of a match, whatever the engine
+#[derive(Debug)]
+pub enum Captures<'c, 't> {
+    Default(&'c regex::Captures<'t>),
+    #[cfg(feature = "fancy")]
+    Fancy(&'c fancy_regex::Captures<'t>),
+    #[cfg(feature = "pcre2")]
+    Pcre2(&'c pcre2::bytes::Captures<'t>),
+}
+
+/// A capture group, by index or by name
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureGroup {
+    Index(usize),
+    Name(String),
+}
+
+impl FromStr for CaptureGroup {
+    type Err = PatternError;
+
+    /// Parses `2` as the group 2 and `version` as the group named `version`
+    fn from_str(group: &str) -> Result<Self, Self::Err> {
+        match group.parse() {
+            Ok(index) => Ok(CaptureGroup::Index(index)),
+            Err(_) if !group.is_empty() => Ok(CaptureGroup::Name(group.to_string())),
+            Err(_) => Err(PatternError::Other("empty capture group".to_string())),
+        }
+    }
+}
+
+impl Captures<'_, '_> {
+    /// Returns the capture group, by index or by name
+    pub fn group(&self, group: &CaptureGroup) -> Option<Match> {
+        match group {
+            CaptureGroup::Index(i) => self.get(*i),
+            CaptureGroup::Name(name) => self.name(name),
+        }
+    }
+
+    /// Returns the group `i`, the group 0 being the whole match
+    pub fn get(&self, i: usize) -> Option<Match> {
+        match self {
+            Captures::Default(caps) => caps.get(i).map(|m| Match {
+                text: m.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+            }),
+            #[cfg(feature = "fancy")]
+            Captures::Fancy(caps) => caps.get(i).map(|m| Match {
+                text: m.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+            }),
+            #[cfg(feature = "pcre2")]
+            Captures::Pcre2(caps) => caps.get(i).map(|m| Match {
+                text: utf8(m.as_bytes()).to_string(),
+                start: m.start(),
+                end: m.end(),
+            }),
+        }
+    }
+
+    /// Returns the group named `name`
+    pub fn name(&self, name: &str) -> Option<Match> {
+        match self {
+            Captures::Default(caps) => caps.name(name).map(|m| Match {
+                text: m.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+            }),
+            #[cfg(feature = "fancy")]
+            Captures::Fancy(caps) => caps.name(name).map(|m| Match {
+                text: m.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+            }),
+            #[cfg(feature = "pcre2")]
+            Captures::Pcre2(caps) => caps.name(name).map(|m| Match {
+                text: utf8(m.as_bytes()).to_string(),
+                start: m.start(),
+                end: m.end(),
+            }),
+        }
+    }
+
+    /// Expands the template (`$1`, `${name}`...) with the groups and appends it to `dst`
+    pub fn expand(&self, template: &str, dst: &mut String) {
+        match self {
+            Captures::Default(caps) => caps.expand(template, dst),
+            #[cfg(feature = "fancy")]
+            Captures::Fancy(caps) => caps.expand(template, dst),
+            #[cfg(feature = "pcre2")]
+            Captures::Pcre2(caps) => expand(template, dst, |group| {
+                let m = match group.parse() {
+                    Ok(i) => caps.get(i),
+                    Err(_) => caps.name(group),
+                };
+                m.map(|m| utf8(m.as_bytes()))
+            }),
+        }
+    }
+}
+
+impl Index<usize> for Captures<'_, '_> {
+    type Output = str;
+
+    /// Panics if the group `i` didn't participate in the match
+    fn index(&self, i: usize) -> &str {
+        match self {
+            Captures::Default(caps) => &caps[i],
+            #[cfg(feature = "fancy")]
+            Captures::Fancy(caps) => &caps[i],
+            #[cfg(feature = "pcre2")]
+            Captures::Pcre2(caps) => utf8(&caps[i]),
+        }
+    }
+}
+
+impl Index<&str> for Captures<'_, '_> {
+    type Output = str;
+
+    /// Panics if the group `name` didn't participate in the match
+    fn index(&self, name: &str) -> &str {
+        match self {
+            Captures::Default(caps) => &caps[name],
+            #[cfg(feature = "fancy")]
+            Captures::Fancy(caps) => &caps[name],
+            #[cfg(feature = "pcre2")]
+            Captures::Pcre2(caps) => utf8(&caps[name]),
+        }
+    }
+}
+
+/// The text of a match of the pcre2 engine, which only searches UTF-8 texts in UTF mode
+#[cfg(feature = "pcre2")]
+fn utf8(bytes: &[u8]) -> &str {
+    std::str::from_utf8(bytes).expect("the matches of a UTF-8 text are UTF-8")
+}
+
+/// Appends the template to `dst`, its groups `$N`, `$name`, `${N}` and `${name}` replaced
+/// like the `regex` crate does, by the text given by `group` or nothing, and `$$` by `$`
+#[cfg(feature = "pcre2")]
+fn expand<'t>(template: &str, dst: &mut String, group: impl Fn(&str) -> Option<&'t str>) {
+    let mut rest = template;
+    while let Some(dollar) = rest.find('$') {
+        dst.push_str(&rest[..dollar]);
+        rest = &rest[dollar + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            dst.push('$');
+            rest = after;
+            continue;
+        }
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", rest),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        // A `$` without a group name is kept
+        if name.is_empty() {
+            dst.push('$');
+        } else {
+            dst.push_str(group(name).unwrap_or(""));
+            rest = after;
+        }
+    }
+    dst.push_str(rest);
+}
+
+/// Configures and compiles a `RegexPattern`, instead of writing inline flags
+///
+/// ```rust
+/// use drgrep::regex::pattern::{PatternError, RegexPattern};
+///
+/// let pattern = RegexPattern::builder(r"^error:.*")
+///     .case_insensitive(true)
+///     .multi_line(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(2, pattern.find_all("ERROR: a\nok\nError: b").len());
+///
+/// let result = RegexPattern::builder(r"\w{100}").size_limit(1000).build();
+/// assert!(matches!(result, Err(PatternError::SizeLimitExceeded(1000))));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexPatternBuilder {
+    pattern: String,
+    engine: Engine,
+    case_insensitive: bool,
+    multi_line: bool,
+    dot_matches_new_line: bool,
+    unicode: bool,
+    size_limit: Option<usize>,
+    dfa_size_limit: Option<usize>,
+}
+
+impl RegexPatternBuilder {
+    /// Creates a builder with the default options of `RegexPattern::new`
+    pub fn new(pattern: &str) -> Self {
+        RegexPatternBuilder {
+            pattern: pattern.to_string(),
+            engine: Engine::Default,
+            case_insensitive: false,
+            multi_line: false,
+            dot_matches_new_line: false,
+            unicode: true,
+            size_limit: None,
+            dfa_size_limit: None,
+        }
+    }
+
+    /// Replaces the pattern, to compile several patterns with the same options
+    pub fn pattern(&mut self, pattern: &str) -> &mut Self {
+        self.pattern = pattern.to_string();
+        self
+    }
+
+    /// Sets the engine compiling the pattern, `Engine::Default` by default
+    pub fn engine(&mut self, engine: Engine) -> &mut Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Ignores the case of the letters (flag `i`)
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Makes `^` and `$` match at the start and the end of each line (flag `m`)
+    pub fn multi_line(&mut self, yes: bool) -> &mut Self {
+        self.multi_line = yes;
+        self
+    }
+
+    /// Makes `.` match the line breaks too (flag `s`)
+    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut Self {
+        self.dot_matches_new_line = yes;
+        self
+    }
+
+    /// Enables the Unicode mode (flag `u`, enabled by default), `\w`, `\d`, `\s` and `\b`
+    /// match ASCII only without it
+    ///
+    /// The dot and the negated classes could then match invalid UTF-8: the patterns using
+    /// them fail to compile unless they enable the mode again, like `(?u:.)`.
+    pub fn unicode(&mut self, yes: bool) -> &mut Self {
+        self.unicode = yes;
+        self
+    }
+
+    /// Sets the limit, in bytes, of the compiled pattern, ignored by the pcre2 engine
+    pub fn size_limit(&mut self, bytes: usize) -> &mut Self {
+        self.size_limit = Some(bytes);
+        self
+    }
+
+    /// Sets the limit, in bytes, of the cache of the lazy DFA
+    pub fn dfa_size_limit(&mut self, bytes: usize) -> &mut Self {
+        self.dfa_size_limit = Some(bytes);
+        self
+    }
+
+    /// Compiles the pattern
+    ///
+    /// The fancy engine backtracks: hitting its backtracking limit at runtime is an error of
+    /// the `try_` methods, like [`RegexPattern::try_is_match`], and no match for the others.
+    pub fn build(&self) -> Result<RegexPattern, PatternError> {
+        let regex = match self.engine {
+            Engine::Default => Compiled::Default(self.compile_default()?),
+            Engine::Fancy => self.compile_fancy()?,
+            Engine::Pcre2 => self.compile_pcre2()?,
+            Engine::Auto => match self.compile_default() {
+                Ok(regex) => Compiled::Default(regex),
+                // The error of the default engine is kept when every engine fails. The pcre2
+                // engine ignores the size limits, so it can't bypass them
+                Err(e) => match self.compile_fancy() {
+                    Ok(regex) => regex,
+                    Err(_) if matches!(e, PatternError::SizeLimitExceeded(_)) => return Err(e),
+                    Err(_) => self.compile_pcre2().map_err(|_| e)?,
+                },
+            },
+        };
+        Ok(RegexPattern {
+            regex,
+            pattern: self.pattern.clone(),
+        })
+    }
+
+    fn compile_default(&self) -> Result<Regex, PatternError> {
+        let mut builder = regex::RegexBuilder::new(&self.pattern);
+        builder
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .unicode(self.unicode);
+        if let Some(limit) = self.size_limit {
+            builder.size_limit(limit);
+        }
+        if let Some(limit) = self.dfa_size_limit {
+            builder.dfa_size_limit(limit);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Compiles the patterns of a set with the options of the builder, its own pattern
+    /// being ignored
+    pub(crate) fn compile_set(&self, patterns: &[RegexPattern]) -> Result<RegexSet, PatternError> {
+        let mut builder = regex::RegexSetBuilder::new(patterns.iter().map(|p| p.get_pattern()));
+        builder
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .unicode(self.unicode);
+        if let Some(limit) = self.size_limit {
+            builder.size_limit(limit);
+        }
+        if let Some(limit) = self.dfa_size_limit {
+            builder.dfa_size_limit(limit);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Returns the pattern with the word boundaries of `Boundary::Word` and of the POSIX `\<`
+    /// and `\>` written as lookarounds, for the backtracking engines which don't support them
+    #[cfg(any(feature = "fancy", feature = "pcre2"))]
+    fn lookaround_boundaries(&self) -> String {
+        self.pattern
+            .replace(r"\b{start-half}", r"(?<!\w)")
+            .replace(r"\b{end-half}", r"(?!\w)")
+            .replace(r"\b{start}", r"(?<!\w)(?=\w)")
+            .replace(r"\b{end}", r"(?<=\w)(?!\w)")
+    }
+
+    #[cfg(feature = "fancy")]
+    fn compile_fancy(&self) -> Result<Compiled, PatternError> {
+        let mut builder = fancy_regex::RegexBuilder::new(&self.lookaround_boundaries());
+        builder
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .unicode_mode(self.unicode);
+        if let Some(limit) = self.size_limit {
+            builder.delegate_size_limit(limit);
+        }
+        if let Some(limit) = self.dfa_size_limit {
+            builder.delegate_dfa_size_limit(limit);
+        }
+        Ok(Compiled::Fancy(builder.build()?))
+    }
+
+    #[cfg(not(feature = "fancy"))]
+    fn compile_fancy(&self) -> Result<Compiled, PatternError> {
+        Err(PatternError::Other(
+            "drgrep was built without the fancy engine".to_string(),
+        ))
+    }
+
+    /// The texts are always UTF-8, `unicode` only changes the classes like `\w`
+    #[cfg(feature = "pcre2")]
+    fn compile_pcre2(&self) -> Result<Compiled, PatternError> {
+        let mut builder = pcre2::bytes::RegexBuilder::new();
+        builder
+            .caseless(self.case_insensitive)
+            .multi_line(self.multi_line)
+            .dotall(self.dot_matches_new_line)
+            .utf(true)
+            .ucp(self.unicode)
+            .jit_if_available(true);
+        Ok(Compiled::Pcre2(
+            builder.build(&self.lookaround_boundaries())?,
+        ))
+    }
+
+    #[cfg(not(feature = "pcre2"))]
+    fn compile_pcre2(&self) -> Result<Compiled, PatternError> {
+        Err(PatternError::Other(
+            "drgrep was built without the pcre2 engine".to_string(),
+        ))
+    }
+}
+
 impl RegexPattern {
     /// Creates a new regex pattern from a pattern string
     pub fn new(pattern: &str) -> Result<Self, PatternError> {
-        let regex = Regex::new(pattern)?;
-        Ok(RegexPattern { regex, pattern: pattern.to_string() })
+        RegexPatternBuilder::new(pattern).build()
+    }
+
+    /// Creates a builder to set the options of the pattern
+    pub fn builder(pattern: &str) -> RegexPatternBuilder {
+        RegexPatternBuilder::new(pattern)
+    }
+
+    /// Creates a new regex pattern compiled by the given engine
+    pub fn with_engine(pattern: &str, engine: Engine) -> Result<Self, PatternError> {
+        RegexPatternBuilder::new(pattern).engine(engine).build()
+    }
+
+    /// Returns the engine which compiled the pattern, never `Engine::Auto`
+    pub fn engine(&self) -> Engine {
+        match self.regex {
+            Compiled::Default(_) => Engine::Default,
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(_) => Engine::Fancy,
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(_) => Engine::Pcre2,
+        }
+    }
+
+    /// Creates a pattern matching the text literally
+    ///
+    /// The case-insensitive matching relies on the Unicode simple case folding of the regex
+    /// engine (`k` matches the Kelvin sign `K`), without allocating a lowercase copy of the
+    /// searched text. Full foldings like `ß`/`SS` are not supported.
+    pub fn literal(text: &str, case_insensitive: bool) -> Result<Self, PatternError> {
+        Self::builder(&regex::escape(text))
+            .case_insensitive(case_insensitive)
+            .build()
+    }
+
+    /// Returns the names of the capture groups, `None` for the unnamed ones
+    ///
+    /// The group 0, the whole match, is the first one.
+    pub fn capture_names(&self) -> Vec<Option<&str>> {
+        match &self.regex {
+            Compiled::Default(regex) => regex.capture_names().collect(),
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(regex) => regex.capture_names().collect(),
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(regex) => regex.capture_names().iter().map(|n| n.as_deref()).collect(),
+        }
+    }
+
+    /// Returns the names of the named capture groups, in the order of the pattern
+    pub fn group_names(&self) -> Vec<&str> {
+        self.capture_names().into_iter().flatten().collect()
+    }
+
+    /// Returns the named groups of every match in the text, by name
+    ///
+    /// The groups which didn't participate in a match are missing from its map. A runtime
+    /// error of the fancy engine counts as no match, see [`RegexPattern::try_named_captures`].
+    ///
+    /// ```
+    /// use drgrep::regex::pattern::RegexPattern;
+    ///
+    /// let pattern = RegexPattern::new(r"(?P<key>\w+)=(?P<value>\d+)?").unwrap();
+    /// let matches = pattern.named_captures("a=1 b=");
+    /// assert_eq!("1", matches[0]["value"].text);
+    /// assert_eq!("b", matches[1]["key"].text);
+    /// assert!(!matches[1].contains_key("value"));
+    /// ```
+    pub fn named_captures(&self, text: &str) -> Vec<HashMap<String, Match>> {
+        self.try_named_captures(text).unwrap_or_default()
+    }
+
+    /// Returns the named groups of every match in the text, or the runtime error of the
+    /// fancy engine
+    pub fn try_named_captures(
+        &self,
+        text: &str,
+    ) -> Result<Vec<HashMap<String, Match>>, PatternError> {
+        let names = self.group_names();
+        let mut matches = Vec::new();
+        self.try_for_each_captures(text, |caps| {
+            matches.push(
+                names
+                    .iter()
+                    .filter_map(|name| caps.name(name).map(|m| (name.to_string(), m)))
+                    .collect(),
+            );
+        })?;
+        Ok(matches)
+    }
+
+    /// Checks if the pattern has the capture group
+    pub fn has_group(&self, group: &CaptureGroup) -> bool {
+        let names = self.capture_names();
+        match group {
+            CaptureGroup::Index(i) => *i < names.len(),
+            CaptureGroup::Name(name) => names.contains(&Some(name.as_str())),
+        }
+    }
+
+    /// Calls `f` with the capture groups of every match in the text
+    ///
+    /// A runtime error of the fancy engine ends the matches, see
+    /// [`RegexPattern::try_for_each_captures`].
+    pub fn for_each_captures<F>(&self, text: &str, f: F)
+    where
+        F: FnMut(&Captures),
+    {
+        let _ = self.try_for_each_captures(text, f);
+    }
+
+    /// Calls `f` with the capture groups of every match in the text, until the fancy engine
+    /// fails at runtime
+    pub fn try_for_each_captures<F>(&self, text: &str, mut f: F) -> Result<(), PatternError>
+    where
+        F: FnMut(&Captures),
+    {
+        match &self.regex {
+            Compiled::Default(regex) => {
+                regex
+                    .captures_iter(text)
+                    .for_each(|caps| f(&Captures::Default(&caps)));
+            }
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(regex) => {
+                for caps in regex.captures_iter(text) {
+                    f(&Captures::Fancy(&caps?));
+                }
+            }
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(regex) => {
+                for caps in regex.captures_iter(text.as_bytes()) {
+                    f(&Captures::Pcre2(&caps?));
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Returns the original pattern string
@@ -65,69 +759,297 @@ impl RegexPattern {
     }
 
     /// Checks if the text matches the pattern
+    ///
+    /// A runtime error of the fancy engine, like exceeding its backtrack limit, counts as
+    /// no match: [`RegexPattern::try_is_match`] reports it.
     pub fn is_match(&self, text: &str) -> bool {
-        self.regex.is_match(text)
+        self.try_is_match(text).unwrap_or(false)
+    }
+
+    /// Checks if the text matches the pattern, or returns the runtime error of the fancy
+    /// engine
+    ///
+    /// ```
+    /// # #[cfg(feature = "fancy")]
+    /// # {
+    /// use drgrep::regex::pattern::{Engine, RegexPattern};
+    ///
+    /// let pattern = RegexPattern::with_engine(r"(a+)+\1b", Engine::Fancy).unwrap();
+    /// assert!(pattern.try_is_match(&"a".repeat(40)).is_err());
+    /// assert!(!pattern.is_match(&"a".repeat(40)));
+    /// # }
+    /// ```
+    pub fn try_is_match(&self, text: &str) -> Result<bool, PatternError> {
+        match &self.regex {
+            Compiled::Default(regex) => Ok(regex.is_match(text)),
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(regex) => Ok(regex.is_match(text)?),
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(regex) => Ok(regex.is_match(text.as_bytes())?),
+        }
     }
 
     /// Finds the first match in the text
+    ///
+    /// A runtime error of the fancy engine counts as no match, see [`RegexPattern::try_find`].
     pub fn find(&self, text: &str) -> Option<Match> {
-        self.regex.find(text).map(|m| Match {
-            text: m.as_str().to_string(),
-            start: m.start(),
-            end: m.end(),
-        })
+        self.try_find(text).unwrap_or(None)
     }
 
-    /// Finds all matches in the text
-    pub fn find_all(&self, text: &str) -> Vec<Match> {
-        self.regex
-            .find_iter(text)
-            .map(|m| Match {
+    /// Finds the first match in the text, or returns the runtime error of the fancy engine
+    pub fn try_find(&self, text: &str) -> Result<Option<Match>, PatternError> {
+        match &self.regex {
+            Compiled::Default(regex) => Ok(regex.find(text).map(|m| Match {
+                text: m.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+            })),
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(regex) => Ok(regex.find(text)?.map(|m| Match {
                 text: m.as_str().to_string(),
                 start: m.start(),
                 end: m.end(),
-            })
-            .collect()
+            })),
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(regex) => Ok(regex.find(text.as_bytes())?.map(|m| Match {
+                text: text[m.start()..m.end()].to_string(),
+                start: m.start(),
+                end: m.end(),
+            })),
+        }
+    }
+
+    /// Finds all matches in the text
+    ///
+    /// A runtime error of the fancy engine counts as no match, see
+    /// [`RegexPattern::try_find_all`].
+    pub fn find_all(&self, text: &str) -> Vec<Match> {
+        self.try_find_all(text).unwrap_or_default()
+    }
+
+    /// Finds all matches in the text, or returns the runtime error of the fancy engine
+    pub fn try_find_all(&self, text: &str) -> Result<Vec<Match>, PatternError> {
+        match &self.regex {
+            Compiled::Default(regex) => Ok(regex
+                .find_iter(text)
+                .map(|m| Match {
+                    text: m.as_str().to_string(),
+                    start: m.start(),
+                    end: m.end(),
+                })
+                .collect()),
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(regex) => regex
+                .find_iter(text)
+                .map(|m| {
+                    let m = m?;
+                    Ok(Match {
+                        text: m.as_str().to_string(),
+                        start: m.start(),
+                        end: m.end(),
+                    })
+                })
+                .collect(),
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(regex) => regex
+                .find_iter(text.as_bytes())
+                .map(|m| {
+                    let m = m?;
+                    Ok(Match {
+                        text: text[m.start()..m.end()].to_string(),
+                        start: m.start(),
+                        end: m.end(),
+                    })
+                })
+                .collect(),
+        }
     }
 
     /// Replaces all occurrences of the pattern with the replacement string
+    ///
+    /// A runtime error of the fancy engine leaves the text unchanged, see
+    /// [`RegexPattern::try_replace_all`].
     pub fn replace_all(&self, text: &str, replacement: &str) -> String {
-        self.regex.replace_all(text, replacement).into_owned()
+        self.try_replace_all(text, replacement)
+            .unwrap_or_else(|_| text.to_string())
+    }
+
+    /// Replaces all occurrences of the pattern with the replacement string, or returns the
+    /// runtime error of the fancy engine
+    pub fn try_replace_all(&self, text: &str, replacement: &str) -> Result<String, PatternError> {
+        match &self.regex {
+            Compiled::Default(regex) => Ok(regex.replace_all(text, replacement).into_owned()),
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(regex) => Ok(regex.try_replacen(text, 0, replacement)?.into_owned()),
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(_) => self.try_replace_all_with_captures(text, |caps| {
+                let mut replaced = String::new();
+                caps.expand(replacement, &mut replaced);
+                replaced
+            }),
+        }
     }
 
     /// Replaces all occurrences of the pattern with the result of a function
     /// The function receives a `regex::Captures` object, allowing access to capture groups.
+    ///
+    /// # Panics
+    ///
+    /// When the pattern is compiled by the fancy or the pcre2 engine, whose captures aren't
+    /// `regex::Captures`: [`RegexPattern::replace_all_with_captures`] accepts every engine.
     pub fn replace_all_with<F>(&self, text: &str, replacement_fn: F) -> String
     where
         F: Fn(&regex::Captures) -> String,
     {
-        self.regex.replace_all(text, replacement_fn).into_owned()
+        match &self.regex {
+            Compiled::Default(regex) => regex.replace_all(text, replacement_fn).into_owned(),
+            #[cfg(any(feature = "fancy", feature = "pcre2"))]
+            _ => {
+                panic!("replace_all_with needs the default engine, use replace_all_with_captures")
+            }
+        }
+    }
+
+    /// Replaces all occurrences of the pattern with the result of a function, whatever the
+    /// engine: the function receives the [`Captures`] of each match
+    ///
+    /// A runtime error of the fancy engine leaves the text unchanged, see
+    /// [`RegexPattern::try_replace_all_with_captures`].
+    pub fn replace_all_with_captures<F>(&self, text: &str, replacement_fn: F) -> String
+    where
+        F: Fn(&Captures) -> String,
+    {
+        self.try_replace_all_with_captures(text, replacement_fn)
+            .unwrap_or_else(|_| text.to_string())
+    }
+
+    /// Replaces all occurrences of the pattern with the result of a function, or returns the
+    /// runtime error of the fancy engine
+    pub fn try_replace_all_with_captures<F>(
+        &self,
+        text: &str,
+        replacement_fn: F,
+    ) -> Result<String, PatternError>
+    where
+        F: Fn(&Captures) -> String,
+    {
+        match &self.regex {
+            Compiled::Default(regex) => Ok(regex
+                .replace_all(text, |caps: &regex::Captures| {
+                    replacement_fn(&Captures::Default(caps))
+                })
+                .into_owned()),
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(regex) => Ok(regex
+                .try_replacen(text, 0, |caps: &fancy_regex::Captures| {
+                    replacement_fn(&Captures::Fancy(caps))
+                })?
+                .into_owned()),
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(regex) => {
+                let mut replaced = String::with_capacity(text.len());
+                let mut last = 0;
+                for caps in regex.captures_iter(text.as_bytes()) {
+                    let caps = caps?;
+                    let m = caps.get(0).expect("the group 0 is the whole match");
+                    replaced.push_str(&text[last..m.start()]);
+                    replaced.push_str(&replacement_fn(&Captures::Pcre2(&caps)));
+                    last = m.end();
+                }
+                replaced.push_str(&text[last..]);
+                Ok(replaced)
+            }
+        }
     }
 
     /// Splits the text according to the pattern
+    ///
+    /// A runtime error of the fancy engine counts as no match, see [`RegexPattern::try_split`].
     pub fn split(&self, text: &str) -> Vec<String> {
-        self.regex.split(text).map(|s| s.to_string()).collect()
+        self.try_split(text)
+            .unwrap_or_else(|_| vec![text.to_string()])
+    }
+
+    /// Splits the text according to the pattern, or returns the runtime error of the fancy
+    /// engine
+    pub fn try_split(&self, text: &str) -> Result<Vec<String>, PatternError> {
+        match &self.regex {
+            Compiled::Default(regex) => Ok(regex.split(text).map(|s| s.to_string()).collect()),
+            #[cfg(feature = "fancy")]
+            Compiled::Fancy(regex) => regex.split(text).map(|s| Ok(s?.to_string())).collect(),
+            #[cfg(feature = "pcre2")]
+            Compiled::Pcre2(regex) => {
+                let mut pieces = Vec::new();
+                let mut last = 0;
+                for m in regex.find_iter(text.as_bytes()) {
+                    let m = m?;
+                    pieces.push(text[last..m.start()].to_string());
+                    last = m.end();
+                }
+                pieces.push(text[last..].to_string());
+                Ok(pieces)
+            }
+        }
     }
 }
 
-// Utility functions
+/// Checks if the pattern contains an uppercase literal character, as used by the smart case
+///
+/// The escape sequences (`\W`, `\S`, `\p{Lu}`...) and the group names don't count.
+///
+/// ```
+/// use drgrep::regex::pattern::has_uppercase;
+///
+/// assert!(has_uppercase(r"Error\s+\d"));
+/// assert!(!has_uppercase(r"error\S+\p{Lu}(?P<Name>\w)"));
+/// ```
+pub fn has_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some('p' | 'P') = chars.next() {
+                    if chars.peek() == Some(&'{') {
+                        chars.find(|c| *c == '}');
+                    } else {
+                        chars.next();
+                    }
+                }
+            }
+            '(' if chars.peek() == Some(&'?') => {
+                chars.next();
+                if chars.peek() == Some(&'P') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&'<') {
+                    chars.find(|c| *c == '>');
+                }
+            }
+            c if c.is_uppercase() => return true,
+            _ => (),
+        }
+    }
+    false
+}
+
+// Utility functions, compiling the patterns through the global `PatternCache`
 pub fn is_match(pattern: &str, text: &str) -> Result<bool, PatternError> {
-    let p = RegexPattern::new(pattern)?;
+    let p = PatternCache::global().get(pattern)?;
     Ok(p.is_match(text))
 }
 
 pub fn find(pattern: &str, text: &str) -> Result<Option<Match>, PatternError> {
-    let p = RegexPattern::new(pattern)?;
+    let p = PatternCache::global().get(pattern)?;
     Ok(p.find(text))
 }
 
 pub fn find_all(pattern: &str, text: &str) -> Result<Vec<Match>, PatternError> {
-    let p = RegexPattern::new(pattern)?;
+    let p = PatternCache::global().get(pattern)?;
     Ok(p.find_all(text))
 }
 
 pub fn replace_all(pattern: &str, text: &str, replacement: &str) -> Result<String, PatternError> {
-    let p = RegexPattern::new(pattern)?;
+    let p = PatternCache::global().get(pattern)?;
     Ok(p.replace_all(text, replacement))
 }
 
@@ -139,12 +1061,24 @@ pub fn replace_all_with<F>(
 where
     F: Fn(&regex::Captures) -> String,
 {
-    let p = RegexPattern::new(pattern)?;
+    let p = PatternCache::global().get(pattern)?;
     Ok(p.replace_all_with(text, replacement_fn))
 }
 
+pub fn replace_all_with_captures<F>(
+    pattern: &str,
+    text: &str,
+    replacement_fn: F,
+) -> Result<String, PatternError>
+where
+    F: Fn(&Captures) -> String,
+{
+    let p = PatternCache::global().get(pattern)?;
+    Ok(p.replace_all_with_captures(text, replacement_fn))
+}
+
 pub fn split(pattern: &str, text: &str) -> Result<Vec<String>, PatternError> {
-    let p = RegexPattern::new(pattern)?;
+    let p = PatternCache::global().get(pattern)?;
     Ok(p.split(text))
 }
 
@@ -273,13 +1207,239 @@ mod tests {
         assert_eq!(m.text, "abbbc");
     }
 
+    #[test]
+    fn test_engines() {
+        let doubled = r"(\w+)\s+\1";
+        assert!(matches!(
+            RegexPattern::new(doubled),
+            Err(PatternError::RegexError(_))
+        ));
+        // The default engine is kept when it can compile the pattern
+        let pattern = RegexPattern::with_engine(r"\d+", Engine::Auto).unwrap();
+        assert_eq!(Engine::Default, pattern.engine());
+        assert!(RegexPattern::with_engine("(", Engine::Auto).is_err());
+        assert_eq!(Engine::Auto, "auto".parse().unwrap());
+        assert!("perl".parse::<Engine>().is_err());
+        assert_eq!(Engine::Pcre2, "pcre2".parse().unwrap());
+        assert_eq!(cfg!(feature = "pcre2"), Engine::Pcre2.is_available());
+    }
+
+    #[test]
+    fn test_builder_flags() {
+        let pattern = RegexPattern::builder("a.c")
+            .dot_matches_new_line(true)
+            .build()
+            .unwrap();
+        assert!(pattern.is_match("a\nc"));
+        assert!(!RegexPattern::new("a.c").unwrap().is_match("a\nc"));
+
+        let pattern = RegexPattern::builder(r"^\w+$")
+            .unicode(false)
+            .build()
+            .unwrap();
+        assert!(pattern.is_match("cafe"));
+        assert!(!pattern.is_match("café"));
+
+        let pattern = RegexPattern::builder("^b$")
+            .multi_line(true)
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        assert_eq!(2, pattern.find("a\nB\nc").unwrap().start);
+        assert_eq!("^b$", pattern.get_pattern());
+    }
+
+    #[test]
+    fn test_capture_groups() {
+        let pattern = RegexPattern::new(r#"(\w+) = "(?P<value>[^"]*)""#).unwrap();
+        assert_eq!(vec![None, None, Some("value")], pattern.capture_names());
+        assert!(pattern.has_group(&"2".parse().unwrap()));
+        assert!(pattern.has_group(&"value".parse().unwrap()));
+        assert!(!pattern.has_group(&"3".parse().unwrap()));
+        assert!(!pattern.has_group(&"name".parse().unwrap()));
+
+        let mut values = Vec::new();
+        pattern.for_each_captures(r#"a = "1", b = "2""#, |caps| {
+            values.push(
+                caps.group(&CaptureGroup::Name("value".to_string()))
+                    .unwrap()
+                    .text,
+            );
+        });
+        assert_eq!(vec!["1", "2"], values);
+
+        // The regex::Captures of the default engine, or the captures of any engine
+        let pattern = RegexPattern::new(r"(\d+)").unwrap();
+        let doubled = |n: &str| (n.parse::<u32>().unwrap() * 2).to_string();
+        assert_eq!(
+            "2 4",
+            pattern.replace_all_with("1 2", |caps| doubled(&caps[1]))
+        );
+        assert_eq!(
+            "2 4",
+            pattern.replace_all_with_captures("1 2", |caps| doubled(&caps[1]))
+        );
+        if cfg!(feature = "fancy") {
+            let pattern = RegexPattern::with_engine(r"(\d+)(?!x)", Engine::Fancy).unwrap();
+            assert_eq!(
+                "2 2x",
+                pattern.replace_all_with_captures("1 2x", |caps| doubled(&caps[1]))
+            );
+        }
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let result = RegexPattern::builder(r"\w{50}").size_limit(100).build();
+        assert!(matches!(result, Err(PatternError::SizeLimitExceeded(100))));
+        let result = RegexPattern::builder(r"\w{50}")
+            .engine(Engine::Auto)
+            .size_limit(100)
+            .build();
+        assert!(matches!(result, Err(PatternError::SizeLimitExceeded(100))));
+        assert!(RegexPattern::builder(r"\w{50}")
+            .dfa_size_limit(1 << 20)
+            .build()
+            .is_ok());
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn test_fancy_engine() {
+        let pattern = RegexPattern::with_engine(r"foo(?!bar)", Engine::Fancy).unwrap();
+        assert_eq!(Engine::Fancy, pattern.engine());
+        let matches = pattern.find_all("foobar foobaz foo");
+        assert_eq!(
+            vec![7, 14],
+            matches.iter().map(|m| m.start).collect::<Vec<_>>()
+        );
+
+        let pattern = RegexPattern::with_engine(r"(?<w>\w+) \k<w>", Engine::Auto).unwrap();
+        assert_eq!("a is fine", pattern.replace_all("a is is fine", "$w"));
+        let result =
+            pattern.replace_all_with_captures("it it works", |caps| caps["w"].to_uppercase());
+        assert_eq!("IT works", result);
+
+        let pattern =
+            RegexPattern::with_engine(&Boundary::Word.wrap(r"(a)\1"), Engine::Auto).unwrap();
+        assert!(pattern.is_match("an aa b"));
+        assert!(!pattern.is_match("baab"));
+    }
+
+    #[cfg(feature = "pcre2")]
+    #[test]
+    fn test_pcre2_engine() {
+        // Recursion, balanced parentheses
+        let pattern = RegexPattern::with_engine(r"\((?:[^()]++|(?0))*\)", Engine::Pcre2).unwrap();
+        assert_eq!(Engine::Pcre2, pattern.engine());
+        assert_eq!("(a(b)c)", pattern.find("f(a(b)c) + (d").unwrap().text);
+        // The only engine supporting the recursion
+        let pattern = RegexPattern::with_engine(r"\((?:[^()]++|(?0))*\)", Engine::Auto).unwrap();
+        assert_eq!(Engine::Pcre2, pattern.engine());
+
+        let pattern = RegexPattern::builder(r"(?<w>\w+) \k<w>")
+            .engine(Engine::Pcre2)
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        assert_eq!(vec![None, Some("w")], pattern.capture_names());
+        assert_eq!(
+            "Été$ is fine",
+            pattern.replace_all("Été été is fine", "${w}$$")
+        );
+        assert_eq!("[é]$", pattern.replace_all("é É", "[$1]${2}$"));
+        let result =
+            pattern.replace_all_with_captures("it IT works", |caps| caps["w"].to_uppercase());
+        assert_eq!("IT works", result);
+        assert_eq!(vec!["a", "b", ""], pattern.split("ax xbx x"));
+        assert_eq!(4, pattern.named_captures("no, x x")[0]["w"].start);
+
+        let pattern = RegexPattern::with_engine(&Boundary::Word.wrap("é"), Engine::Pcre2).unwrap();
+        assert!(pattern.is_match("un é."));
+        assert!(!pattern.is_match("été"));
+        let pattern = RegexPattern::builder(r"^\w+$")
+            .engine(Engine::Pcre2)
+            .unicode(false)
+            .build()
+            .unwrap();
+        assert!(!pattern.is_match("café"));
+        assert!(matches!(
+            RegexPattern::with_engine("(", Engine::Pcre2),
+            Err(PatternError::Pcre2Error(_))
+        ));
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn test_fancy_runtime_errors() {
+        // Exceeds the backtrack limit of the fancy engine
+        let pattern = RegexPattern::with_engine(r"(a+)+\1b", Engine::Fancy).unwrap();
+        let text = "a".repeat(40);
+        assert!(pattern.try_find(&text).is_err());
+        assert!(pattern.try_find_all(&text).is_err());
+        assert!(pattern.try_named_captures(&text).is_err());
+        assert!(pattern.try_replace_all(&text, "x").is_err());
+        assert!(pattern.try_split(&text).is_err());
+        assert!(matches!(
+            pattern.try_is_match(&text),
+            Err(PatternError::FancyError(_))
+        ));
+        // The infallible methods see no match
+        assert!(pattern.find_all(&text).is_empty());
+        assert_eq!(text, pattern.replace_all(&text, "x"));
+        assert_eq!("x", pattern.try_replace_all("aab", "x").unwrap());
+    }
+
+    #[test]
+    fn test_word_boundary() {
+        let pattern = RegexPattern::new(&Boundary::Word.wrap("-v")).unwrap();
+        assert!(pattern.is_match("drgrep -v"));
+        assert!(!pattern.is_match("drgrep --verbose"));
+
+        let pattern = RegexPattern::new(&Boundary::Word.wrap("café")).unwrap();
+        assert!(pattern.is_match("un café."));
+        assert!(!pattern.is_match("cafés"));
+
+        assert!(Boundary::Word.accepts("an id", 3, 5));
+        assert!(!Boundary::Word.accepts("an idé", 3, 5));
+        assert!(!Boundary::Word.accepts("valid", 3, 5));
+    }
+
+    #[test]
+    fn test_line_boundary() {
+        let pattern = RegexPattern::new(&Boundary::Line.wrap("a|b")).unwrap();
+        assert!(pattern.is_match("b"));
+        assert!(!pattern.is_match("ab"));
+
+        assert!(Boundary::Line.accepts("abc", 0, 3));
+        assert!(!Boundary::Line.accepts("abc", 0, 2));
+        assert!(Boundary::None.accepts("abc", 1, 2));
+    }
+
+    #[test]
+    fn test_literal_unicode_case_folding() {
+        let pattern = RegexPattern::literal("straße", true).unwrap();
+        assert!(pattern.is_match("STRAẞE"));
+        let pattern = RegexPattern::literal("kelvin", true).unwrap();
+        assert!(pattern.is_match("\u{212A}ELVIN"));
+        let pattern = RegexPattern::literal("a.b", false).unwrap();
+        assert!(!pattern.is_match("axb"));
+        assert!(!pattern.is_match("A.B"));
+    }
+
+    #[test]
+    fn test_has_uppercase() {
+        assert!(has_uppercase("Foo"));
+        assert!(has_uppercase("(?i)É"));
+        assert!(!has_uppercase(r"\W\D\P{Greek}"));
+        assert!(!has_uppercase(r"(?<Word>\w+)"));
+        assert!(has_uppercase(r"(?<word>\w+)X"));
+    }
+
     #[test]
     fn test_invalid_pattern() {
         let result = RegexPattern::new("[");
         assert!(result.is_err());
-        match result.unwrap_err() {
-            PatternError::RegexError(_) => assert!(true),
-            _ => assert!(false),
-        }
+        assert!(matches!(result.unwrap_err(), PatternError::RegexError(_)));
     }
 }
\ No newline at end of file
@@ -0,0 +1,586 @@
+//! # Module Posix
+//!
+//! Translates the POSIX basic (BRE, `grep -G`) and extended (ERE, `grep -E`) regular
+//! expressions into the syntax of `RegexPattern`, with the GNU extensions used by the shell
+//! scripts: `\|`, `\+` and `\?` in BRE, `\<`, `\>`, `\b`, `\w`, `\s` and the back-references.
+//!
+//! ```rust
+//! use drgrep::regex::posix::{translate, Syntax};
+//!
+//! assert_eq!(r"(foo){2}", translate(r"\(foo\)\{2\}", Syntax::Basic).unwrap());
+//! assert_eq!(r"a\+[[:alpha:]]", translate(r"a+[[:alpha:]]", Syntax::Basic).unwrap());
+//! assert_eq!(r"(a|b)+\{", translate(r"(a|b)+{", Syntax::Extended).unwrap());
+//!
+//! let err = translate(r"[[:alfa:]]", Syntax::Basic).unwrap_err();
+//! assert_eq!("Syntax error at column 2: unknown character class [:alfa:]", err.to_string());
+//! ```
+//!
+//! In BRE, `*` is a literal at the start of an expression, and so are `^` and `$` outside
+//! the start and the end. In a bracket expression the backslash is a literal.
+
+use std::str::FromStr;
+
+use super::pattern::PatternError;
+
+/// The flavour of POSIX regular expressions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Syntax {
+    /// Basic regular expressions, where `\(`, `\)`, `\{`, `\}` are the operators
+    Basic,
+    /// Extended regular expressions, where `(`, `)`, `{`, `}`, `|`, `+`, `?` are the operators
+    Extended,
+}
+
+impl FromStr for Syntax {
+    type Err = PatternError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "basic" | "bre" => Ok(Syntax::Basic),
+            "extended" | "ere" => Ok(Syntax::Extended),
+            _ => Err(PatternError::Other(format!(
+                "unknown POSIX syntax '{}'",
+                name
+            ))),
+        }
+    }
+}
+
+/// The character classes of the bracket expressions
+const CLASSES: [&str; 12] = [
+    "alnum", "alpha", "blank", "cntrl", "digit", "graph", "lower", "print", "punct", "space",
+    "upper", "xdigit",
+];
+
+/// The escapes kept as they are
+const PERL_ESCAPES: [char; 8] = ['b', 'B', 'w', 'W', 's', 'S', '.', '*'];
+
+fn error(column: usize, message: String) -> PatternError {
+    PatternError::Syntax { column, message }
+}
+
+/// Escapes a character matched literally
+fn literal(c: char) -> String {
+    ::regex::escape(c.encode_utf8(&mut [0; 4]))
+}
+
+struct Translator {
+    chars: Vec<char>,
+    syntax: Syntax,
+    pos: usize,
+    out: String,
+    /// Start in `out` of the last atom a quantifier applies to
+    atom: Option<usize>,
+    /// The last atom already has a quantifier
+    quantified: bool,
+    /// Column and output start of the open groups
+    groups: Vec<(usize, usize)>,
+    closed_groups: usize,
+}
+
+impl Translator {
+    /// The 1-based column of the character at `pos`
+    fn column(&self, pos: usize) -> usize {
+        pos + 1
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    /// Checks if the position starts an expression, where `*` is a literal in BRE
+    fn at_expression_start(&self) -> bool {
+        self.atom.is_none()
+    }
+
+    /// Appends an atom a quantifier can apply to
+    fn push_atom(&mut self, text: &str) {
+        self.atom = Some(self.out.len());
+        self.quantified = false;
+        self.out.push_str(text);
+    }
+
+    /// Appends an anchor or an alternation, which no quantifier applies to
+    fn push_operator(&mut self, text: &str) {
+        self.atom = None;
+        self.quantified = false;
+        self.out.push_str(text);
+    }
+
+    /// Appends a quantifier to the last atom, nesting it when it is already quantified
+    fn push_quantifier(&mut self, quantifier: &str) {
+        let start = self.atom.unwrap_or(self.out.len());
+        if self.quantified {
+            self.out.insert_str(start, "(?:");
+            self.out.push(')');
+        }
+        self.out.push_str(quantifier);
+        self.quantified = true;
+    }
+
+    fn translate(mut self) -> Result<String, PatternError> {
+        while let Some(c) = self.peek(0) {
+            match (c, self.syntax) {
+                ('\\', _) => self.escape()?,
+                ('[', _) => self.bracket()?,
+                ('.', _) => {
+                    self.push_atom(".");
+                    self.pos += 1;
+                }
+                ('*', _) => {
+                    if self.at_expression_start() {
+                        self.push_atom(r"\*");
+                    } else {
+                        self.push_quantifier("*");
+                    }
+                    self.pos += 1;
+                }
+                ('^', Syntax::Extended) => {
+                    self.push_operator("^");
+                    self.pos += 1;
+                }
+                ('^', Syntax::Basic) => {
+                    // An anchor at the start of the expression or of a group or alternative
+                    let anchor = self.pos == 0
+                        || (self.pos >= 2
+                            && self.chars[self.pos - 2] == '\\'
+                            && matches!(self.chars[self.pos - 1], '(' | '|'));
+                    if anchor {
+                        self.push_operator("^");
+                    } else {
+                        self.push_atom(r"\^");
+                    }
+                    self.pos += 1;
+                }
+                ('$', Syntax::Extended) => {
+                    self.push_operator("$");
+                    self.pos += 1;
+                }
+                ('$', Syntax::Basic) => {
+                    // An anchor at the end of the expression or of a group or alternative
+                    let anchor = self.peek(1).is_none()
+                        || (self.peek(1) == Some('\\') && matches!(self.peek(2), Some(')' | '|')));
+                    if anchor {
+                        self.push_operator("$");
+                    } else {
+                        self.push_atom(r"\$");
+                    }
+                    self.pos += 1;
+                }
+                ('(', Syntax::Extended) => self.open_group(1),
+                (')', Syntax::Extended) => {
+                    if self.groups.is_empty() {
+                        // Like GNU grep, an unmatched `)` is a literal
+                        self.push_atom(r"\)");
+                        self.pos += 1;
+                    } else {
+                        self.close_group(1);
+                    }
+                }
+                ('|', Syntax::Extended) => {
+                    self.push_operator("|");
+                    self.pos += 1;
+                }
+                ('+' | '?', Syntax::Extended) => {
+                    if self.at_expression_start() {
+                        self.push_atom(&literal(c));
+                    } else {
+                        self.push_quantifier(&c.to_string());
+                    }
+                    self.pos += 1;
+                }
+                ('{', Syntax::Extended) => {
+                    // A brace not starting an interval is a literal
+                    if self.at_expression_start() || !matches!(self.peek(1), Some('0'..='9' | ','))
+                    {
+                        self.push_atom(r"\{");
+                        self.pos += 1;
+                    } else {
+                        self.interval(1)?;
+                    }
+                }
+                (c, _) => {
+                    self.push_atom(&literal(c));
+                    self.pos += 1;
+                }
+            }
+        }
+        if let Some((column, _)) = self.groups.last() {
+            let open = if self.syntax == Syntax::Basic {
+                r"\("
+            } else {
+                "("
+            };
+            return Err(error(*column, format!("unmatched {}", open)));
+        }
+        Ok(self.out)
+    }
+
+    /// Translates the escape at the position
+    fn escape(&mut self) -> Result<(), PatternError> {
+        let column = self.column(self.pos);
+        let Some(c) = self.peek(1) else {
+            return Err(error(column, "trailing backslash".to_string()));
+        };
+        match (c, self.syntax) {
+            ('(', Syntax::Basic) => self.open_group(2),
+            (')', Syntax::Basic) => {
+                if self.groups.is_empty() {
+                    return Err(error(column, r"unmatched \)".to_string()));
+                }
+                self.close_group(2);
+            }
+            ('|', Syntax::Basic) => {
+                self.push_operator("|");
+                self.pos += 2;
+            }
+            ('+' | '?', Syntax::Basic) => {
+                if self.at_expression_start() {
+                    self.push_atom(&literal(c));
+                } else {
+                    self.push_quantifier(&c.to_string());
+                }
+                self.pos += 2;
+            }
+            ('{', Syntax::Basic) => {
+                if self.at_expression_start() {
+                    return Err(error(
+                        column,
+                        r"the interval \{ doesn't follow an expression".to_string(),
+                    ));
+                }
+                self.interval(2)?;
+            }
+            ('}', Syntax::Basic) => {
+                return Err(error(column, r"unmatched \}".to_string()));
+            }
+            ('<', _) => {
+                self.push_operator(r"\b{start}");
+                self.pos += 2;
+            }
+            ('>', _) => {
+                self.push_operator(r"\b{end}");
+                self.pos += 2;
+            }
+            ('`', _) => {
+                self.push_operator(r"\A");
+                self.pos += 2;
+            }
+            ('\'', _) => {
+                self.push_operator(r"\z");
+                self.pos += 2;
+            }
+            ('b' | 'B', _) => {
+                self.push_operator(&format!("\\{}", c));
+                self.pos += 2;
+            }
+            ('1'..='9', _) => {
+                let group = c.to_digit(10).unwrap_or(0) as usize;
+                if group > self.closed_groups {
+                    return Err(error(
+                        column,
+                        format!(r"invalid back reference \{}, no such group before it", c),
+                    ));
+                }
+                self.push_atom(&format!("\\{}", c));
+                self.pos += 2;
+            }
+            (c, _) if PERL_ESCAPES.contains(&c) => {
+                self.push_atom(&format!("\\{}", c));
+                self.pos += 2;
+            }
+            (c, _) if c.is_alphanumeric() => {
+                return Err(error(
+                    column,
+                    format!(r"unknown escape \{}, POSIX has no such sequence", c),
+                ));
+            }
+            (c, _) => {
+                self.push_atom(&literal(c));
+                self.pos += 2;
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens a group, its operator being `len` characters long
+    fn open_group(&mut self, len: usize) {
+        self.groups.push((self.column(self.pos), self.out.len()));
+        self.push_operator("(");
+        self.pos += len;
+    }
+
+    /// Closes the last open group, its operator being `len` characters long
+    fn close_group(&mut self, len: usize) {
+        if let Some((_, start)) = self.groups.pop() {
+            self.out.push(')');
+            self.atom = Some(start);
+            self.quantified = false;
+            self.closed_groups += 1;
+        }
+        self.pos += len;
+    }
+
+    /// Translates the interval at the position, its braces being `len` characters long
+    fn interval(&mut self, len: usize) -> Result<(), PatternError> {
+        let column = self.column(self.pos);
+        let close = if len == 2 { r"\}" } else { "}" };
+        let mut pos = self.pos + len;
+        let number = |pos: &mut usize| {
+            let start = *pos;
+            while self.chars.get(*pos).is_some_and(|c| c.is_ascii_digit()) {
+                *pos += 1;
+            }
+            self.chars[start..*pos].iter().collect::<String>()
+        };
+        let min = number(&mut pos);
+        let max = if self.chars.get(pos) == Some(&',') {
+            pos += 1;
+            Some(number(&mut pos))
+        } else {
+            None
+        };
+        let closed: String = self.chars.iter().skip(pos).take(len).collect();
+        if pos >= self.chars.len() {
+            let open = if len == 2 { r"\{" } else { "{" };
+            return Err(error(column, format!("unmatched {}", open)));
+        }
+        if closed != close {
+            return Err(error(
+                self.column(pos),
+                format!("invalid interval, expected {}", close),
+            ));
+        }
+        let parse = |n: &str| n.parse::<u32>().ok();
+        let quantifier = match (min.as_str(), max.as_deref()) {
+            ("", None) | ("", Some("")) => {
+                return Err(error(column, "the interval has no bound".to_string()));
+            }
+            (min, None) => format!("{{{}}}", min),
+            (min, Some("")) => format!("{{{},}}", min),
+            (min, Some(max)) => {
+                let lower = if min.is_empty() { Some(0) } else { parse(min) };
+                if lower > parse(max) {
+                    return Err(error(
+                        column,
+                        format!("invalid interval, {} is greater than {}", min, max),
+                    ));
+                }
+                format!("{{{},{}}}", lower.unwrap_or(0), max)
+            }
+        };
+        self.push_quantifier(&quantifier);
+        self.pos = pos + len;
+        Ok(())
+    }
+
+    /// Translates the bracket expression at the position
+    fn bracket(&mut self) -> Result<(), PatternError> {
+        let column = self.column(self.pos);
+        let mut pos = self.pos + 1;
+        let mut class = String::from("[");
+        if self.chars.get(pos) == Some(&'^') {
+            class.push('^');
+            pos += 1;
+        }
+        let first = pos;
+        // The items, `None` standing for a character class
+        let mut items: Vec<(Option<char>, String)> = Vec::new();
+        loop {
+            let Some(&c) = self.chars.get(pos) else {
+                return Err(error(column, "unmatched [".to_string()));
+            };
+            // A `]` first in the list is a literal
+            if c == ']' && pos > first {
+                pos += 1;
+                break;
+            }
+            if c == '[' && matches!(self.chars.get(pos + 1), Some(':' | '=' | '.')) {
+                let kind = self.chars[pos + 1];
+                let start = pos + 2;
+                let end = (start..self.chars.len().saturating_sub(1))
+                    .find(|&i| self.chars[i] == kind && self.chars[i + 1] == ']')
+                    .ok_or_else(|| error(self.column(pos), format!("unmatched [{}", kind)))?;
+                let name: String = self.chars[start..end].iter().collect();
+                match kind {
+                    ':' => {
+                        if !CLASSES.contains(&name.as_str()) {
+                            return Err(error(
+                                self.column(pos),
+                                format!("unknown character class [:{}:]", name),
+                            ));
+                        }
+                        items.push((None, format!("[:{}:]", name)));
+                    }
+                    _ => {
+                        let mut chars = name.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => items.push((Some(c), literal(c))),
+                            _ => {
+                                return Err(error(
+                                    self.column(pos),
+                                    format!(
+                                        "unsupported collating element [{}{}{}]",
+                                        kind, name, kind
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+                pos = end + 2;
+                continue;
+            }
+            items.push((Some(c), literal(c)));
+            pos += 1;
+        }
+        // Joins the ranges, a `-` first or last in the list being a literal
+        let mut i = 0;
+        while i < items.len() {
+            let is_range = i + 2 < items.len() && items[i + 1].0 == Some('-');
+            match (&items[i], is_range) {
+                (_, true) if items[i].0.is_none() || items[i + 2].0.is_none() => {
+                    return Err(error(
+                        column,
+                        "invalid range, a character class can't be a bound".to_string(),
+                    ));
+                }
+                ((Some(lo), lo_text), true) => {
+                    let (Some(hi), hi_text) = &items[i + 2] else {
+                        unreachable!("the classes are rejected above");
+                    };
+                    if lo > hi {
+                        return Err(error(
+                            column,
+                            format!("invalid range end, {} comes before {}", hi, lo),
+                        ));
+                    }
+                    class.push_str(&format!("{}-{}", lo_text, hi_text));
+                    i += 3;
+                }
+                ((_, text), _) => {
+                    class.push_str(text);
+                    i += 1;
+                }
+            }
+        }
+        class.push(']');
+        self.push_atom(&class);
+        self.pos = pos;
+        Ok(())
+    }
+}
+
+/// Translates a POSIX regular expression into the syntax of `RegexPattern`
+///
+/// The errors are `PatternError::Syntax`, with the 1-based column of the offending
+/// character.
+pub fn translate(pattern: &str, syntax: Syntax) -> Result<String, PatternError> {
+    Translator {
+        chars: pattern.chars().collect(),
+        syntax,
+        pos: 0,
+        out: String::with_capacity(pattern.len()),
+        atom: None,
+        quantified: false,
+        groups: Vec::new(),
+        closed_groups: 0,
+    }
+    .translate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::pattern::RegexPattern;
+
+    fn bre(pattern: &str) -> String {
+        translate(pattern, Syntax::Basic).unwrap()
+    }
+
+    fn ere(pattern: &str) -> String {
+        translate(pattern, Syntax::Extended).unwrap()
+    }
+
+    fn column(pattern: &str, syntax: Syntax) -> usize {
+        match translate(pattern, syntax) {
+            Err(PatternError::Syntax { column, .. }) => column,
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_basic() {
+        assert_eq!(r"(foo){2}", bre(r"\(foo\)\{2\}"));
+        assert_eq!(r"a{1,}b{0,3}", bre(r"a\{1,\}b\{,3\}"));
+        assert_eq!(r"a\(b\)\{c|d", bre(r"a(b){c\|d"));
+        assert_eq!(r"\*a*", bre(r"*a*"));
+        assert_eq!(r"^\*x\^y\$$", bre(r"^*x^y$$"));
+        assert_eq!(r"(^a$|b)", bre(r"\(^a$\|b\)"));
+        assert_eq!(r"a+b?", bre(r"a\+b\?"));
+        assert_eq!(r"\+a", bre(r"\+a"));
+        assert_eq!(r"(ab)\1", bre(r"\(ab\)\1"));
+        assert_eq!(r"\b{start}\w+\b{end}", bre(r"\<\w\+\>"));
+        assert_eq!(r"1\.5\[", bre(r"1\.5\["));
+    }
+
+    #[test]
+    fn test_extended() {
+        assert_eq!(r"(a|b)+c?", ere(r"(a|b)+c?"));
+        assert_eq!(r"x{2,5}\{y\{\}", ere(r"x{2,5}{y{}"));
+        assert_eq!(r"\(a\)b\)", ere(r"\(a\)b)"));
+        assert_eq!(r"\*+a|\?", ere(r"*+a|?"));
+        // A quantified atom is grouped before another quantifier
+        assert_eq!(r"(?:a*)+", ere(r"a*+"));
+        assert_eq!(r"a\|b", ere(r"a\|b"));
+    }
+
+    #[test]
+    fn test_brackets() {
+        assert_eq!(r"[[:alpha:]_]", bre(r"[[:alpha:]_]"));
+        assert_eq!(r"[\]a\-]", bre(r"[]a-]"));
+        assert_eq!(r"[^\\a-z]", bre(r"[^\a-z]"));
+        assert_eq!(r"[\[e\.]", ere(r"[[[=e=][...]]"));
+        assert_eq!(r"[\&\&\~]", ere(r"[&&~]"));
+    }
+
+    #[test]
+    fn test_translated_patterns_match() {
+        let pattern = RegexPattern::new(&bre(r"\(ab\)\{2\}[[:digit:]]")).unwrap();
+        assert!(pattern.is_match("xabab7"));
+        assert!(!pattern.is_match("ab7"));
+        let pattern = RegexPattern::new(&ere(r"^[[:upper:]][^]]*]$")).unwrap();
+        assert!(pattern.is_match("A[x]"));
+        assert!(!pattern.is_match("a[x]"));
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn test_back_references() {
+        use crate::regex::pattern::Engine;
+
+        let pattern = RegexPattern::with_engine(&bre(r"\<\([a-z]*\) \1\>"), Engine::Auto).unwrap();
+        assert_eq!(Engine::Fancy, pattern.engine());
+        assert_eq!("the the", pattern.find("see the the cat").unwrap().text);
+        assert!(!pattern.is_match("see the theory"));
+    }
+
+    #[test]
+    fn test_errors_point_to_the_column() {
+        assert_eq!(3, column(r"ab[cd", Syntax::Basic));
+        assert_eq!(3, column(r"a[[:alfa:]]", Syntax::Basic));
+        assert_eq!(2, column(r"a\(b", Syntax::Basic));
+        assert_eq!(3, column(r"ab\)", Syntax::Basic));
+        assert_eq!(2, column(r"a(b", Syntax::Extended));
+        assert_eq!(2, column(r"a\{2", Syntax::Basic));
+        assert_eq!(5, column(r"a\{2x\}", Syntax::Basic));
+        assert_eq!(2, column(r"a{3,1}", Syntax::Extended));
+        assert_eq!(1, column(r"[z-a]", Syntax::Extended));
+        assert_eq!(2, column(r"a\1", Syntax::Basic));
+        assert_eq!(2, column(r"a\d", Syntax::Basic));
+        assert_eq!(2, column(r"a\", Syntax::Extended));
+        assert_eq!(1, column(r"\{1\}", Syntax::Basic));
+        // The columns count the characters, not the bytes
+        assert_eq!(3, column(r"éé[", Syntax::Basic));
+    }
+}
@@ -0,0 +1,172 @@
+//! # Module Set
+//!
+//! Matches several patterns at once with a `regex::RegexSet`: the text is scanned a single
+//! time whatever the number of patterns, and the indexes of every matching pattern are
+//! reported.
+//!
+//! The patterns compiled by the fancy engine can't be part of a `RegexSet`: a set holding
+//! some of them tries its patterns one after the other.
+//!
+//! ```rust
+//! use drgrep::regex::set::PatternSet;
+//!
+//! let set = PatternSet::new(["error", r"warn(ing)?", r"\d{3}"]).unwrap();
+//! assert_eq!(vec![0, 2], set.matches("error 500"));
+//! assert!(!set.is_match("all good"));
+//! ```
+
+use regex::RegexSet;
+
+use super::pattern::{Engine, PatternError, RegexPattern, RegexPatternBuilder};
+
+/// A set of patterns matched together
+#[derive(Debug)]
+pub struct PatternSet {
+    /// `None` when some patterns need the fancy engine
+    set: Option<RegexSet>,
+    patterns: Vec<RegexPattern>,
+}
+
+impl PatternSet {
+    /// Creates a new set from the pattern strings
+    pub fn new<I, S>(patterns: I) -> Result<Self, PatternError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::with_engine(patterns, Engine::Default)
+    }
+
+    /// Creates a new set from the pattern strings, compiled by the given engine
+    pub fn with_engine<I, S>(patterns: I, engine: Engine) -> Result<Self, PatternError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::with_builder(patterns, RegexPatternBuilder::new("").engine(engine))
+    }
+
+    /// Creates a new set from the pattern strings, compiled with the options of the builder
+    ///
+    /// The pattern of the builder itself is ignored.
+    pub fn with_builder<I, S>(
+        patterns: I,
+        builder: &RegexPatternBuilder,
+    ) -> Result<Self, PatternError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = builder.clone();
+        let patterns = patterns
+            .into_iter()
+            .map(|p| builder.pattern(p.as_ref()).build())
+            .collect::<Result<Vec<_>, _>>()?;
+        let set = if patterns.iter().all(|p| p.engine() == Engine::Default) {
+            Some(builder.compile_set(&patterns)?)
+        } else {
+            None
+        };
+        Ok(PatternSet { set, patterns })
+    }
+
+    /// Returns the number of patterns in the set
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Returns `true` if the set doesn't contain any pattern
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns the patterns of the set
+    pub fn patterns(&self) -> &[RegexPattern] {
+        &self.patterns
+    }
+
+    /// Checks if any pattern of the set matches the text
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.set {
+            Some(set) => set.is_match(text),
+            None => self.patterns.iter().any(|p| p.is_match(text)),
+        }
+    }
+
+    /// Returns the indexes of the patterns matching the text, in ascending order
+    ///
+    /// A pattern failing at runtime in the fancy engine doesn't match, see
+    /// [`PatternSet::try_matches`].
+    pub fn matches(&self, text: &str) -> Vec<usize> {
+        match &self.set {
+            Some(set) => set.matches(text).into_iter().collect(),
+            None => (0..self.patterns.len())
+                .filter(|i| self.patterns[*i].is_match(text))
+                .collect(),
+        }
+    }
+
+    /// Returns the indexes of the patterns matching the text, or the first runtime error of
+    /// the fancy engine
+    pub fn try_matches(&self, text: &str) -> Result<Vec<usize>, PatternError> {
+        match &self.set {
+            Some(set) => Ok(set.matches(text).into_iter().collect()),
+            None => {
+                let mut matched = Vec::new();
+                for (i, pattern) in self.patterns.iter().enumerate() {
+                    if pattern.try_is_match(text)? {
+                        matched.push(i);
+                    }
+                }
+                Ok(matched)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let set = PatternSet::new(["foo", "bar", r"^\d+$"]).unwrap();
+        assert_eq!(3, set.len());
+        assert_eq!(vec![0, 1], set.matches("foobar"));
+        assert_eq!(vec![2], set.matches("42"));
+        assert!(set.matches("baz").is_empty());
+        assert_eq!("bar", set.patterns()[1].get_pattern());
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let result = PatternSet::new(["foo", "("]);
+        assert!(matches!(result, Err(PatternError::RegexError(_))));
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn test_fancy_patterns() {
+        let set =
+            PatternSet::with_engine([r"(\w+) \1", r"foo(?!bar)", "baz"], Engine::Auto).unwrap();
+        assert_eq!(vec![0, 2], set.matches("baz baz"));
+        assert_eq!(vec![1], set.matches("foo!"));
+        assert!(!set.is_match("foobar"));
+    }
+
+    #[test]
+    fn test_builder_options() {
+        let mut builder = RegexPatternBuilder::new("");
+        builder.case_insensitive(true);
+        let set = PatternSet::with_builder(["foo", "bar"], &builder).unwrap();
+        assert_eq!(vec![0, 1], set.matches("FOO Bar"));
+        assert_eq!("foo", set.patterns()[0].get_pattern());
+    }
+
+    #[test]
+    fn test_empty_set() {
+        let set = PatternSet::new(Vec::<String>::new()).unwrap();
+        assert!(set.is_empty());
+        assert!(!set.is_match("anything"));
+    }
+}
@@ -0,0 +1,157 @@
+//! # Diff Module
+//!
+//! Renders the changes of a [`Rewrite`] as a unified diff, the format understood by
+//! `patch` and `git apply`. The lines keep their original endings, a last line without one
+//! being followed by the `\ No newline at end of file` marker.
+
+use super::Rewrite;
+
+/// Number of unchanged lines shown around each change
+pub const CONTEXT_LINES: usize = 3;
+
+/// Appends a line of the diff, `ending` being the original line ending of `body`
+fn push_line(out: &mut String, prefix: char, body: &str, ending: &str) {
+    out.push(prefix);
+    out.push_str(body);
+    if ending.is_empty() {
+        out.push_str("\n\\ No newline at end of file\n");
+    } else {
+        out.push_str(ending);
+    }
+}
+
+/// Builds the unified diff between `original` and the rewritten content
+///
+/// Returns an empty string when the rewrite doesn't change anything.
+///
+/// # Examples
+///
+/// ```
+/// use drgrep::regex::pattern::RegexPattern;
+/// use drgrep::replace::{diff::unified_diff, rewrite};
+///
+/// let pattern = RegexPattern::new("foo").unwrap();
+/// let original = "foo\nbar\n";
+/// let result = rewrite(&pattern, "baz", original).unwrap();
+/// assert_eq!(
+///     "--- a/file.txt\n+++ b/file.txt\n@@ -1,2 +1,2 @@\n-foo\n+baz\n bar\n",
+///     unified_diff("file.txt", original, &result)
+/// );
+/// ```
+pub fn unified_diff(path: &str, original: &str, rewrite: &Rewrite) -> String {
+    if rewrite.changes.is_empty() {
+        return String::new();
+    }
+    // Each line with its ending, `\n`, `\r\n` or none for the last line
+    let lines: Vec<(&str, &str)> = original
+        .split_inclusive('\n')
+        .map(|line| {
+            let body = super::strip_line_ending(line);
+            (body, &line[body.len()..])
+        })
+        .collect();
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path, path);
+
+    // Group the changes whose context would overlap into the same hunk
+    let mut hunks: Vec<&[super::LineChange]> = Vec::new();
+    let mut start = 0;
+    for i in 1..=rewrite.changes.len() {
+        if i == rewrite.changes.len()
+            || rewrite.changes[i].line - rewrite.changes[i - 1].line > 2 * CONTEXT_LINES + 1
+        {
+            hunks.push(&rewrite.changes[start..i]);
+            start = i;
+        }
+    }
+
+    // Lines added (or removed) by the previous hunks shift the new line numbers
+    let mut offset: isize = 0;
+    for hunk in hunks {
+        let first = hunk[0].line.saturating_sub(CONTEXT_LINES);
+        let last = (hunk[hunk.len() - 1].line + CONTEXT_LINES + 1).min(lines.len());
+        let mut body = String::new();
+        let mut new_len = 0;
+        let mut changes = hunk.iter().peekable();
+        for (idx, (line, ending)) in lines.iter().enumerate().take(last).skip(first) {
+            match changes.peek() {
+                Some(change) if change.line == idx => {
+                    push_line(&mut body, '-', &change.before, ending);
+                    // The lines inserted by the replacement end with its `\n`, the last one
+                    // with the original ending
+                    let mut new_lines = change.after.split('\n').peekable();
+                    while let Some(new_line) = new_lines.next() {
+                        let new_ending = if new_lines.peek().is_some() {
+                            "\n"
+                        } else {
+                            ending
+                        };
+                        push_line(&mut body, '+', new_line, new_ending);
+                        new_len += 1;
+                    }
+                    changes.next();
+                }
+                _ => {
+                    push_line(&mut body, ' ', line, ending);
+                    new_len += 1;
+                }
+            }
+        }
+        let old_len = last - first;
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            first + 1,
+            old_len,
+            (first as isize + offset + 1),
+            new_len
+        ));
+        out.push_str(&body);
+        offset += new_len as isize - old_len as isize;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::pattern::RegexPattern;
+    use crate::replace::rewrite;
+
+    #[test]
+    fn test_no_changes() {
+        let pattern = RegexPattern::new("nothing").unwrap();
+        let result = rewrite(&pattern, "x", "a\nb\n").unwrap();
+        assert_eq!("", unified_diff("f", "a\nb\n", &result));
+    }
+
+    #[test]
+    fn test_separate_hunks() {
+        let pattern = RegexPattern::new("x").unwrap();
+        let original = "x\n1\n2\n3\n4\n5\n6\n7\n8\nx\n";
+        let result = rewrite(&pattern, "y", original).unwrap();
+        let diff = unified_diff("f", original, &result);
+        assert!(diff.contains("@@ -1,4 +1,4 @@\n-x\n+y\n 1\n 2\n 3\n"));
+        assert!(diff.contains("@@ -7,4 +7,4 @@\n 6\n 7\n 8\n-x\n+y\n"));
+    }
+
+    #[test]
+    fn test_multiline_replacement_shifts_next_hunk() {
+        let pattern = RegexPattern::new("x").unwrap();
+        let original = "x\n1\n2\n3\n4\n5\n6\n7\n8\nx\n";
+        let result = rewrite(&pattern, "y\nz", original).unwrap();
+        let diff = unified_diff("f", original, &result);
+        assert!(diff.contains("@@ -1,4 +1,5 @@\n-x\n+y\n+z\n"));
+        assert!(diff.contains("@@ -7,4 +8,5 @@\n"));
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let pattern = RegexPattern::new(r"foo(\d+)").unwrap();
+        let original = "foo1\r\nkeep\r\nfoo22";
+        let result = rewrite(&pattern, "bar$1", original).unwrap();
+        assert_eq!(
+            "--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n-foo1\r\n+bar1\r\n keep\r\n\
+             -foo22\n\\ No newline at end of file\n+bar22\n\\ No newline at end of file\n",
+            unified_diff("f", original, &result)
+        );
+    }
+}
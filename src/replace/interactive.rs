@@ -0,0 +1,290 @@
+//! # Interactive Module
+//!
+//! Per-match confirmation of the replacements, like `sed` with confirmation or the
+//! `:%s///gc` command of vim. For each match the line is shown before and after the
+//! replacement and one of the following answers is expected:
+//!
+//! - `y` replace this match
+//! - `n` skip this match
+//! - `a` replace this match and all the remaining ones without asking
+//! - `q` stop, the matches accepted so far are still written
+//! - `e` type the replacement text for this match
+//!
+//! The [`Prompter`] reads its answers from any `BufRead`, so a scripted input can drive it.
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, StdinLock, Stdout, Write};
+
+use super::{strip_line_ending, LineChange, Rewrite};
+use crate::color::config::Color;
+use crate::regex::pattern::RegexPattern;
+
+/// An answer to the confirmation prompt
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    Yes,
+    No,
+    All,
+    Quit,
+    Edit(String),
+}
+
+/// Asks the confirmation of every replacement on `output`, reading the answers from `input`
+pub struct Prompter<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    accept_all: bool,
+    quit: bool,
+}
+
+impl Prompter<StdinLock<'static>, Stdout> {
+    /// Creates a prompter on the standard input and output
+    pub fn stdio() -> Self {
+        Prompter::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    /// Creates a new prompter from an input and an output
+    pub fn new(input: R, output: W) -> Self {
+        Prompter {
+            input,
+            output,
+            accept_all: false,
+            quit: false,
+        }
+    }
+
+    /// Returns `true` once the user answered `q` or the input is exhausted
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Returns the output of the prompter
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(strip_line_ending(&line).to_string()))
+    }
+
+    /// Shows the match and its replacement then reads the answer
+    ///
+    /// An exhausted input is read as [`Answer::Quit`], invalid answers are asked again.
+    pub fn ask(
+        &mut self,
+        source: &str,
+        idx: usize,
+        line: &str,
+        span: (usize, usize),
+        replacement: &str,
+    ) -> io::Result<Answer> {
+        let (start, end) = span;
+        writeln!(
+            self.output,
+            "{}{}:{}{}",
+            Color::BRIGHT_BLUE,
+            source,
+            idx + 1,
+            Color::RESET
+        )?;
+        writeln!(
+            self.output,
+            "- {}{}{}{}{}",
+            &line[..start],
+            Color::RED,
+            &line[start..end],
+            Color::RESET,
+            &line[end..]
+        )?;
+        writeln!(
+            self.output,
+            "+ {}{}{}{}{}",
+            &line[..start],
+            Color::GREEN,
+            replacement,
+            Color::RESET,
+            &line[end..]
+        )?;
+        loop {
+            write!(
+                self.output,
+                "Replace this match? [y]es, [n]o, [a]ll, [q]uit, [e]dit: "
+            )?;
+            self.output.flush()?;
+            let answer = match self.read_line()? {
+                Some(answer) => answer,
+                None => return Ok(Answer::Quit),
+            };
+            match answer.trim() {
+                "y" | "Y" | "yes" => return Ok(Answer::Yes),
+                "n" | "N" | "no" => return Ok(Answer::No),
+                "a" | "A" | "all" => return Ok(Answer::All),
+                "q" | "Q" | "quit" => return Ok(Answer::Quit),
+                "e" | "E" | "edit" => {
+                    write!(self.output, "Replacement: ")?;
+                    self.output.flush()?;
+                    return match self.read_line()? {
+                        Some(text) => Ok(Answer::Edit(text)),
+                        None => Ok(Answer::Quit),
+                    };
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    /// Applies `template` to the matches of `pattern` in `content` that the user accepts
+    ///
+    /// Like [`super::rewrite`] the replacement is done line by line and preserves the
+    /// line endings. Nothing is asked anymore once the user has quit.
+    pub fn rewrite(
+        &mut self,
+        pattern: &RegexPattern,
+        template: &str,
+        source: &str,
+        content: &str,
+    ) -> io::Result<Rewrite> {
+        let mut result = String::with_capacity(content.len());
+        let mut changes = Vec::new();
+        let mut replacements = 0;
+        for (idx, line) in content.split_inclusive('\n').enumerate() {
+            let body = strip_line_ending(line);
+            if self.quit || !pattern.try_is_match(body).map_err(io::Error::other)? {
+                result.push_str(line);
+                continue;
+            }
+            let accepted = RefCell::new(0);
+            let error = RefCell::new(None);
+            let this = RefCell::new(&mut *self);
+            let replaced = pattern.try_replace_all_with_captures(body, |caps| {
+                let whole = caps.get(0).unwrap();
+                let mut this = this.borrow_mut();
+                if this.quit || error.borrow().is_some() {
+                    return whole.text.clone();
+                }
+                let mut expanded = String::new();
+                caps.expand(template, &mut expanded);
+                let answer = if this.accept_all {
+                    Ok(Answer::Yes)
+                } else {
+                    this.ask(source, idx, body, (whole.start, whole.end), &expanded)
+                };
+                match answer {
+                    Ok(Answer::Yes) => {
+                        *accepted.borrow_mut() += 1;
+                        expanded
+                    }
+                    Ok(Answer::All) => {
+                        this.accept_all = true;
+                        *accepted.borrow_mut() += 1;
+                        expanded
+                    }
+                    Ok(Answer::Edit(text)) => {
+                        *accepted.borrow_mut() += 1;
+                        text
+                    }
+                    Ok(Answer::No) => whole.text.clone(),
+                    Ok(Answer::Quit) => {
+                        this.quit = true;
+                        whole.text.clone()
+                    }
+                    Err(e) => {
+                        *error.borrow_mut() = Some(e);
+                        whole.text.clone()
+                    }
+                }
+            });
+            if let Some(e) = error.into_inner() {
+                return Err(e);
+            }
+            let replaced = replaced.map_err(io::Error::other)?;
+            result.push_str(&replaced);
+            result.push_str(&line[body.len()..]);
+            if replaced != body {
+                replacements += accepted.into_inner();
+                changes.push(LineChange {
+                    line: idx,
+                    before: body.to_string(),
+                    after: replaced,
+                });
+            }
+        }
+        Ok(Rewrite {
+            content: result,
+            changes,
+            replacements,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn scripted(answers: &str) -> Prompter<Cursor<Vec<u8>>, Vec<u8>> {
+        Prompter::new(Cursor::new(answers.as_bytes().to_vec()), Vec::new())
+    }
+
+    #[test]
+    fn test_yes_and_no() {
+        let pattern = RegexPattern::new(r"foo(\d)").unwrap();
+        let mut prompter = scripted("y\nn\ny\n");
+        let result = prompter
+            .rewrite(&pattern, "bar$1", "f", "foo1 foo2\nfoo3\n")
+            .unwrap();
+        assert_eq!("bar1 foo2\nbar3\n", result.content);
+        assert_eq!(2, result.replacements);
+        assert_eq!(2, result.changes.len());
+        assert!(!prompter.has_quit());
+    }
+
+    #[test]
+    fn test_all_stops_asking() {
+        let pattern = RegexPattern::new("a").unwrap();
+        let mut prompter = scripted("n\na\n");
+        let result = prompter
+            .rewrite(&pattern, "b", "f", "a\na\na\na\n")
+            .unwrap();
+        assert_eq!("a\nb\nb\nb\n", result.content);
+        assert_eq!(3, result.replacements);
+    }
+
+    #[test]
+    fn test_quit_keeps_accepted_matches() {
+        let pattern = RegexPattern::new("a").unwrap();
+        let mut prompter = scripted("y\nq\n");
+        let result = prompter
+            .rewrite(&pattern, "b", "f", "a a a\r\na\r\n")
+            .unwrap();
+        assert_eq!("b a a\r\na\r\n", result.content);
+        assert_eq!(1, result.replacements);
+        assert!(prompter.has_quit());
+    }
+
+    #[test]
+    fn test_edit_and_invalid_answers() {
+        let pattern = RegexPattern::new("a").unwrap();
+        let mut prompter = scripted("what\ne\ncustom\n");
+        let result = prompter.rewrite(&pattern, "b", "f", "xa\n").unwrap();
+        assert_eq!("xcustom\n", result.content);
+        let output = String::from_utf8(prompter.into_output()).unwrap();
+        assert_eq!(2, output.matches("Replace this match?").count());
+    }
+
+    #[test]
+    fn test_exhausted_input_quits() {
+        let pattern = RegexPattern::new("a").unwrap();
+        let mut prompter = scripted("");
+        let result = prompter.rewrite(&pattern, "b", "f", "a\na\n").unwrap();
+        assert_eq!("a\na\n", result.content);
+        assert!(result.changes.is_empty());
+        assert!(prompter.has_quit());
+    }
+}
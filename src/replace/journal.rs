@@ -0,0 +1,342 @@
+//! # Journal Module
+//!
+//! Safety net for the in-place replacements. Every run writing files saves the original
+//! contents of the modified files in its own directory of the journal, identified by a
+//! run ID, so it can be reverted later:
+//!
+//! ```sh
+//! $ drgrep --undo-list # Shows the past runs
+//! $ drgrep --undo # Restores the files of the last run
+//! $ drgrep --undo 1760790000-4242 # Restores the files of a specific run
+//! ```
+//!
+//! The journal lives in `$XDG_STATE_HOME/drgrep/journal` (or `~/.local/state/drgrep/journal`),
+//! the `DRGREP_JOURNAL_DIR` env variable overrides it.
+//!
+//! Each run directory holds a `manifest` and the saved files. The manifest stores, for each
+//! file, the hash of the content written by drgrep: a file modified since the run is never
+//! overwritten by an undo.
+
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, process};
+
+use super::write_atomic;
+
+const MANIFEST: &str = "manifest";
+
+/// Returns the directory of the journal
+pub fn root() -> PathBuf {
+    if let Ok(dir) = env::var("DRGREP_JOURNAL_DIR") {
+        return PathBuf::from(dir);
+    }
+    let state = env::var("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| {
+            env::var("HOME")
+                .or_else(|_| env::var("USERPROFILE"))
+                .map(|home| Path::new(&home).join(".local").join("state"))
+        })
+        .unwrap_or_else(|_| env::temp_dir());
+    state.join("drgrep").join("journal")
+}
+
+/// 64-bit FNV-1a hash, stable across runs and Rust versions
+fn hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Formats a unix timestamp as an UTC date `YYYY-MM-DD HH:MM:SS`
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil date from the days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// A run of the in-place replacement being recorded
+#[derive(Debug)]
+pub struct Journal {
+    id: String,
+    dir: PathBuf,
+    files: usize,
+}
+
+impl Journal {
+    /// Creates the directory of a new run in the journal `root`
+    pub fn create(root: &Path) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?
+            .as_secs();
+        let id = format!("{}-{}", timestamp, process::id());
+        let dir = root.join(&id);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(MANIFEST), format!("timestamp\t{}\n", timestamp))?;
+        Ok(Journal { id, dir, files: 0 })
+    }
+
+    /// Returns the ID of the run
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the number of files recorded
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    /// Saves the `original` content of `path` before it gets replaced by `new`
+    ///
+    /// Must be called before writing the file, so the original is never lost.
+    pub fn record(&mut self, path: &Path, original: &str, new: &str) -> io::Result<()> {
+        let path = fs::canonicalize(path)?;
+        let saved = self.files.to_string();
+        fs::write(self.dir.join(&saved), original)?;
+        let mut manifest = OpenOptions::new()
+            .append(true)
+            .open(self.dir.join(MANIFEST))?;
+        writeln!(
+            manifest,
+            "{}\t{:016x}\t{:016x}\t{}",
+            saved,
+            hash(original.as_bytes()),
+            hash(new.as_bytes()),
+            path.display()
+        )?;
+        self.files += 1;
+        Ok(())
+    }
+}
+
+/// A file recorded in a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    saved: String,
+    original_hash: u64,
+    written_hash: u64,
+    pub path: PathBuf,
+}
+
+/// A past run of the journal
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunInfo {
+    pub id: String,
+    pub timestamp: u64,
+    pub entries: Vec<Entry>,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Whether `id` has the `<timestamp>-<pid>` format of the run IDs, so an ID given on the
+/// command line can't point outside of the journal
+pub fn is_run_id(id: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    id.split_once('-')
+        .is_some_and(|(timestamp, pid)| digits(timestamp) && digits(pid))
+}
+
+/// Reads the manifest of the run `id`
+pub fn read_run(root: &Path, id: &str) -> io::Result<RunInfo> {
+    if !is_run_id(id) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid run ID '{}', expected <timestamp>-<pid>", id),
+        ));
+    }
+    let manifest = fs::read_to_string(root.join(id).join(MANIFEST))?;
+    let mut lines = manifest.lines();
+    let timestamp = lines
+        .next()
+        .and_then(|l| l.strip_prefix("timestamp\t"))
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| invalid(format!("corrupted manifest for the run {}", id)))?;
+    let parse_entry = |line: &str| {
+        let fields: Vec<&str> = line.splitn(4, '\t').collect();
+        match fields[..] {
+            [saved, original, written, path] => Some(Entry {
+                saved: saved.to_string(),
+                original_hash: u64::from_str_radix(original, 16).ok()?,
+                written_hash: u64::from_str_radix(written, 16).ok()?,
+                path: PathBuf::from(path),
+            }),
+            _ => None,
+        }
+    };
+    let entries = lines
+        .map(|line| {
+            parse_entry(line)
+                .ok_or_else(|| invalid(format!("corrupted manifest for the run {}", id)))
+        })
+        .collect::<io::Result<Vec<Entry>>>()?;
+    Ok(RunInfo {
+        id: id.to_string(),
+        timestamp,
+        entries,
+    })
+}
+
+/// Lists the runs of the journal, oldest first
+pub fn list_runs(root: &Path) -> io::Result<Vec<RunInfo>> {
+    let mut runs = Vec::new();
+    let dir = match fs::read_dir(root) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(runs),
+        Err(e) => return Err(e),
+    };
+    for entry in dir {
+        let entry = entry?;
+        if let Some(id) = entry.file_name().to_str() {
+            if let Ok(run) = read_run(root, id) {
+                runs.push(run);
+            }
+        }
+    }
+    runs.sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+    Ok(runs)
+}
+
+/// Restores the files of the run `id`, or of the last run when `None`
+///
+/// Every file is checked before restoring anything: if one of them changed since the run
+/// nothing is restored. Files already holding their original content are skipped. The run
+/// is removed from the journal once restored, the restored paths are returned.
+pub fn undo(root: &Path, id: Option<&str>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let run = match id {
+        Some(id) => read_run(root, id).map_err(|e| format!("unknown run {}: {}", id, e))?,
+        None => list_runs(root)?
+            .pop()
+            .ok_or("the journal doesn't contain any run")?,
+    };
+
+    let mut to_restore = Vec::new();
+    for entry in &run.entries {
+        let current =
+            fs::read(&entry.path).map_err(|e| format!("{}: {}", entry.path.display(), e))?;
+        let current = hash(&current);
+        if current == entry.original_hash {
+            continue;
+        }
+        if current != entry.written_hash {
+            return Err(format!(
+                "{} changed since the run {}, nothing was restored",
+                entry.path.display(),
+                run.id
+            )
+            .into());
+        }
+        to_restore.push(entry);
+    }
+
+    let dir = root.join(&run.id);
+    let mut restored = Vec::new();
+    for entry in to_restore {
+        let original = fs::read_to_string(dir.join(&entry.saved))?;
+        write_atomic(&entry.path, &original)?;
+        restored.push(entry.path.clone());
+    }
+    fs::remove_dir_all(dir)?;
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::create_temp_dir;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!("1970-01-01 00:00:00", format_timestamp(0));
+        assert_eq!("2000-02-29 12:30:05", format_timestamp(951_827_405));
+        assert_eq!("2025-04-17 20:40:36", format_timestamp(1_744_922_436));
+    }
+
+    #[test]
+    fn test_record_list_and_undo() -> Result<(), Box<dyn Error>> {
+        let dir = create_temp_dir()?;
+        let root = dir.path().join("journal");
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "old\n")?;
+
+        let mut journal = Journal::create(&root)?;
+        journal.record(&file, "old\n", "new\n")?;
+        write_atomic(&file, "new\n")?;
+
+        let runs = list_runs(&root)?;
+        assert_eq!(1, runs.len());
+        assert_eq!(journal.id(), runs[0].id);
+        assert_eq!(1, runs[0].entries.len());
+
+        let restored = undo(&root, Some(journal.id()))?;
+        assert_eq!(1, restored.len());
+        assert_eq!("old\n", fs::read_to_string(&file)?);
+        assert!(list_runs(&root)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_refuses_modified_files() -> Result<(), Box<dyn Error>> {
+        let dir = create_temp_dir()?;
+        let root = dir.path().join("journal");
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "old\n")?;
+
+        let mut journal = Journal::create(&root)?;
+        journal.record(&file, "old\n", "new\n")?;
+        fs::write(&file, "edited by hand\n")?;
+
+        assert!(undo(&root, None).is_err());
+        assert_eq!("edited by hand\n", fs::read_to_string(&file)?);
+        assert_eq!(1, list_runs(&root)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_without_runs() -> Result<(), Box<dyn Error>> {
+        let dir = create_temp_dir()?;
+        assert!(undo(dir.path(), None).is_err());
+        assert!(undo(dir.path(), Some("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_rejects_paths() -> Result<(), Box<dyn Error>> {
+        let dir = create_temp_dir()?;
+        let root = dir.path().join("journal");
+        // A run-like directory outside of the journal is never read nor removed
+        let outside = dir.path().join("1760790000-4242");
+        fs::create_dir_all(&outside)?;
+        fs::write(outside.join(MANIFEST), "timestamp\t1760790000\n")?;
+        for id in ["../1760790000-4242", "1760790000-4242/..", "..", "a-1", "1-", "12"] {
+            let error = undo(&root, Some(id)).unwrap_err().to_string();
+            assert!(error.contains("invalid run ID"), "{}", error);
+        }
+        assert!(outside.join(MANIFEST).exists());
+        assert!(is_run_id("1760790000-4242"));
+        Ok(())
+    }
+}
@@ -0,0 +1,461 @@
+//! # Replace Module
+//!
+//! In-place search and replace over the files found by the workspace scanning.
+//!
+//! The replacement is applied line by line with [`RegexPattern::replace_all`], so the
+//! line endings (`\n` or `\r\n`) of the files are always preserved. By default a run
+//! is a dry run printing a unified diff of the changes, the files are only written
+//! when `--write` is given.
+//!
+//! ```sh
+//! # Preview the changes
+//! $ drgrep -r 'foo(\d+)' --replace 'bar$1' --in-place -p src
+//!
+//! # Apply them
+//! $ drgrep -r 'foo(\d+)' --replace 'bar$1' --in-place --write -p src
+//!
+//! # Confirm every match, only the accepted ones are written
+//! $ drgrep -r 'foo(\d+)' --replace 'bar$1' --interactive -p src
+//! ```
+//!
+//! The original contents of the written files are saved in the [`journal`], so a run
+//! can be reverted with `drgrep --undo`.
+
+pub mod diff;
+pub mod interactive;
+pub mod journal;
+
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::fs::{self, DirEntry, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{env, process};
+
+use crate::color::config::Color;
+use crate::regex::diagnostic::Diagnostic;
+use crate::regex::pattern::{Boundary, PatternError, RegexPattern};
+use crate::{print_colored, utilities, Config, DrgrepError};
+use interactive::Prompter;
+use journal::Journal;
+
+type StdioPrompter = Prompter<io::StdinLock<'static>, io::Stdout>;
+
+/// A line modified by a rewrite
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineChange {
+    /// Zero-based index of the line in the original content
+    pub line: usize,
+    /// The line before the replacement, without its line ending
+    pub before: String,
+    /// The line after the replacement, without its line ending
+    pub after: String,
+}
+
+/// Result of the replacement over a whole content
+#[derive(Debug, Clone)]
+pub struct Rewrite {
+    /// The new content
+    pub content: String,
+    /// The modified lines in order
+    pub changes: Vec<LineChange>,
+    /// The total number of replaced matches
+    pub replacements: usize,
+}
+
+/// Counters reported at the end of an in-place run
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ReplaceSummary {
+    pub files: usize,
+    pub replacements: usize,
+}
+
+/// Returns the line without its `\n` or `\r\n` terminator
+pub(crate) fn strip_line_ending(line: &str) -> &str {
+    line.strip_suffix('\n')
+        .map(|l| l.strip_suffix('\r').unwrap_or(l))
+        .unwrap_or(line)
+}
+
+/// Applies `template` to every match of `pattern` in `content`, line by line
+///
+/// Fails with the runtime error of the fancy engine, like exceeding its backtrack limit,
+/// rather than leaving the content unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use drgrep::regex::pattern::RegexPattern;
+/// use drgrep::replace::rewrite;
+///
+/// let pattern = RegexPattern::new(r"v(\d+)").unwrap();
+/// let result = rewrite(&pattern, "version $1", "v1 and v2\r\nnothing\r\n").unwrap();
+/// assert_eq!("version 1 and version 2\r\nnothing\r\n", result.content);
+/// assert_eq!(2, result.replacements);
+/// assert_eq!(1, result.changes.len());
+/// ```
+pub fn rewrite(
+    pattern: &RegexPattern,
+    template: &str,
+    content: &str,
+) -> Result<Rewrite, PatternError> {
+    let mut result = String::with_capacity(content.len());
+    let mut changes = Vec::new();
+    let mut replacements = 0;
+    for (idx, line) in content.split_inclusive('\n').enumerate() {
+        let body = strip_line_ending(line);
+        let replaced = pattern.try_replace_all(body, template)?;
+        if replaced == body {
+            result.push_str(line);
+            continue;
+        }
+        result.push_str(&replaced);
+        result.push_str(&line[body.len()..]);
+        replacements += pattern.try_find_all(body)?.len();
+        changes.push(LineChange {
+            line: idx,
+            before: body.to_string(),
+            after: replaced,
+        });
+    }
+    Ok(Rewrite {
+        content: result,
+        changes,
+        replacements,
+    })
+}
+
+/// Writes `content` to `path` through a temporary file renamed over the original
+///
+/// The permissions of the existing file are copied to the new one, so a reader never
+/// observes a partially written file and executables stay executable.
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    let permissions = fs::metadata(path)?.permissions();
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let tmp_path = path.with_file_name(format!(
+        ".{}.drgrep-{}.tmp",
+        file_name.to_string_lossy(),
+        process::id()
+    ));
+    let written = (|| {
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        tmp.write_all(content.as_bytes())?;
+        tmp.sync_all()?;
+        fs::set_permissions(&tmp_path, permissions)?;
+        fs::rename(&tmp_path, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    written
+}
+
+/// Builds the pattern matching a literal search key, within the boundary of `-w`/`-x`
+pub fn key_pattern(
+    key: &str,
+    sensitive: bool,
+    boundary: Boundary,
+) -> Result<RegexPattern, PatternError> {
+    RegexPattern::builder(&boundary.wrap(&::regex::escape(key)))
+        .case_insensitive(!sensitive)
+        .build()
+}
+
+/// Prints a unified diff with the usual diff coloration
+pub fn print_diff(diff: &str) {
+    for line in diff.split_terminator('\n') {
+        // The `\r` of the CRLF lines is printed after the coloration, the diff still applying
+        let (line, cr) = line.strip_suffix('\r').map_or((line, ""), |l| (l, "\r"));
+        let color = if line.starts_with("---") || line.starts_with("+++") {
+            Color::BOLD
+        } else if line.starts_with("@@") {
+            Color::CYAN
+        } else if line.starts_with('-') {
+            Color::RED
+        } else if line.starts_with('+') {
+            Color::GREEN
+        } else {
+            println!("{}{}", line, cr);
+            continue;
+        };
+        println!("{}{}{}{}", color, line, Color::RESET, cr);
+    }
+}
+
+/// Rewrites a single file, printing its diff in dry run mode
+///
+/// When a prompter is given the user confirms every match and the accepted ones are
+/// written directly. The original content of the written files is saved in the journal
+/// of the run, created in `journal_root` on the first write.
+fn replace_in_file(
+    path: &Path,
+    pattern: &RegexPattern,
+    template: &str,
+    write: bool,
+    prompter: Option<&RefCell<StdioPrompter>>,
+    journal: &RefCell<Option<Journal>>,
+    journal_root: &Path,
+) -> Result<Option<Rewrite>, Box<dyn Error>> {
+    let content = match utilities::can_read_to_utf8(path) {
+        Ok(c) => c,
+        // Binary and non UTF-8 files are never rewritten
+        Err(DrgrepError::Encoding { .. }) => return Ok(None),
+        // The path is printed with the error by the caller
+        Err(DrgrepError::Io { source, .. }) => return Err(source.into()),
+        Err(e) => return Err(e.into()),
+    };
+    let display = path
+        .strip_prefix("./")
+        .unwrap_or(path)
+        .display()
+        .to_string();
+    let result = match prompter {
+        Some(p) => p
+            .borrow_mut()
+            .rewrite(pattern, template, &display, &content)?,
+        None => rewrite(pattern, template, &content)?,
+    };
+    if result.changes.is_empty() {
+        return Ok(None);
+    }
+    if write || prompter.is_some() {
+        let mut journal = journal.borrow_mut();
+        if journal.is_none() {
+            *journal = Some(Journal::create(journal_root)?);
+        }
+        if let Some(j) = journal.as_mut() {
+            j.record(path, &content, &result.content)?;
+        }
+        write_atomic(path, &result.content)?;
+    } else {
+        print_diff(&diff::unified_diff(&display, &content, &result));
+    }
+    Ok(Some(result))
+}
+
+/// Runs the in-place replacement described by the config
+pub fn run(config: &Config) -> Result<(), DrgrepError> {
+    run_with_journal(config, &journal::root())
+}
+
+/// Runs the in-place replacement, saving the written files in the journal `journal_root`
+pub fn run_with_journal(config: &Config, journal_root: &Path) -> Result<(), DrgrepError> {
+    let template = config.replacement.unwrap_or_default();
+    let key = match &config.fixed_strings {
+        Some(fixed) if fixed.patterns().len() == 1 => Some(fixed.patterns()[0].as_str()),
+        Some(_) => return Err("--replace accepts a single pattern".into()),
+        None => config.search_key,
+    };
+    let key_regex;
+    let pattern = match (&config.regex, key) {
+        (Some(reg), _) => reg,
+        (None, Some(key)) => {
+            key_regex = key_pattern(key, config.sensitive, config.boundary)
+                .map_err(|e| Diagnostic::new(key, &e))?;
+            &key_regex
+        }
+        (None, _) if config.pattern_set.is_some() => {
+            return Err("--replace accepts a single pattern".into())
+        }
+        (None, None) => return Err("no search key/regex provided".into()),
+    };
+    if config.paths.is_empty() && config.search_content.is_some() {
+        return Err("--in-place needs a file or directory path".into());
+    }
+    let summary = RefCell::new(ReplaceSummary::default());
+    let prompter = config.interactive.then(|| RefCell::new(Prompter::stdio()));
+    let journal = RefCell::new(None);
+    let errors = Cell::new(0);
+
+    let handle = |path: &Path| {
+        if prompter.as_ref().is_some_and(|p| p.borrow().has_quit()) {
+            return;
+        }
+        match replace_in_file(
+            path,
+            pattern,
+            template,
+            config.write,
+            prompter.as_ref(),
+            &journal,
+            journal_root,
+        ) {
+            Ok(Some(result)) => {
+                let mut s = summary.borrow_mut();
+                s.files += 1;
+                s.replacements += result.replacements;
+            }
+            Ok(None) => (),
+            Err(e) => {
+                errors.set(errors.get() + 1);
+                if !config.no_messages {
+                    eprintln!("{}: {}", path.display(), e);
+                }
+            }
+        }
+    };
+
+    let warn = |e: DrgrepError| {
+        errors.set(errors.get() + 1);
+        if !config.no_messages {
+            eprintln!("drgrep: {}", e);
+        }
+    };
+    let (ignore, ignore_error) = utilities::GitIgnoreFiles::load();
+    if let Some(e) = ignore_error {
+        warn(e);
+    }
+    let current_dir = env::current_dir().unwrap_or_else(|_| PathBuf::new());
+    let roots = if config.paths.is_empty() {
+        vec!["./"]
+    } else {
+        config.paths.clone()
+    };
+    for root in roots {
+        if !Path::new(root).is_dir() {
+            handle(Path::new(root));
+            continue;
+        }
+        utilities::walk(
+            Path::new(root),
+            &ignore,
+            &current_dir,
+            &|f: &DirEntry| {
+                let path = f.path();
+                if config.has_file_type(&path) {
+                    handle(&path);
+                }
+            },
+            &warn,
+        );
+    }
+
+    let summary = summary.into_inner();
+    let message = if config.write || config.interactive {
+        format!(
+            "{} replacement(s) in {} file(s)",
+            summary.replacements, summary.files
+        )
+    } else {
+        format!(
+            "dry run: {} replacement(s) in {} file(s), use --write to apply them",
+            summary.replacements, summary.files
+        )
+    };
+    print_colored!(message.as_str(), Color::BRIGHT_BLUE);
+    if let Some(j) = journal.into_inner() {
+        print_colored!(
+            format!(
+                "journal: {} (revert it with drgrep --undo {})",
+                j.id(),
+                j.id()
+            )
+            .as_str(),
+            Color::BRIGHT_BLUE
+        );
+    }
+    match errors.get() {
+        0 => Ok(()),
+        errors => Err(DrgrepError::Incomplete { errors }),
+    }
+}
+
+/// Restores the files of a run of the journal, see [`journal::undo`]
+pub fn undo(id: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let restored = journal::undo(&journal::root(), id)?;
+    for path in &restored {
+        println!("restored {}", path.display());
+    }
+    print_colored!(
+        format!("{} file(s) restored", restored.len()).as_str(),
+        Color::BRIGHT_BLUE
+    );
+    Ok(())
+}
+
+/// Prints the runs of the journal with their timestamps and file counts
+pub fn undo_list() -> Result<(), Box<dyn Error>> {
+    let runs = journal::list_runs(&journal::root())?;
+    if runs.is_empty() {
+        println!("the journal doesn't contain any run");
+    }
+    for run in runs {
+        print_colored!(run.id.as_str(), Color::BRIGHT_BLUE);
+        println!(
+            "  {} UTC, {} file(s)",
+            journal::format_timestamp(run.timestamp),
+            run.entries.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::create_temp_dir;
+
+    #[test]
+    fn test_rewrite_preserves_line_endings() {
+        let pattern = RegexPattern::new("a").unwrap();
+        let result = rewrite(&pattern, "b", "a\r\nc\na").unwrap();
+        assert_eq!("b\r\nc\nb", result.content);
+        assert_eq!(2, result.replacements);
+        assert_eq!(
+            vec![0, 2],
+            result.changes.iter().map(|c| c.line).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_rewrite_ignores_identity_replacements() {
+        let pattern = RegexPattern::new("(a)").unwrap();
+        let result = rewrite(&pattern, "$1", "aa\n").unwrap();
+        assert_eq!("aa\n", result.content);
+        assert_eq!(0, result.replacements);
+        assert!(result.changes.is_empty());
+    }
+
+    #[cfg(feature = "fancy")]
+    #[test]
+    fn test_rewrite_fails_at_runtime() {
+        use crate::regex::pattern::Engine;
+
+        let pattern = RegexPattern::with_engine(r"(a+)+\1b", Engine::Fancy).unwrap();
+        let content = format!("aab\n{}\n", "a".repeat(40));
+        assert!(rewrite(&pattern, "x", &content).is_err());
+    }
+
+    #[test]
+    fn test_key_pattern_escapes_the_key() {
+        let pattern = key_pattern("a.b", false, Boundary::None).unwrap();
+        assert!(pattern.is_match("A.B"));
+        assert!(!pattern.is_match("axb"));
+        assert!(!key_pattern("a.b", true, Boundary::None)
+            .unwrap()
+            .is_match("A.B"));
+        let pattern = key_pattern("id", true, Boundary::Word).unwrap();
+        assert!(pattern.is_match("the id") && !pattern.is_match("width"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = create_temp_dir()?;
+        let path = dir.path().join("script.sh");
+        fs::write(&path, "echo old\n")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        write_atomic(&path, "echo new\n")?;
+        assert_eq!("echo new\n", fs::read_to_string(&path)?);
+        assert_eq!(0o755, fs::metadata(&path)?.permissions().mode() & 0o777);
+        assert_eq!(1, fs::read_dir(dir.path())?.count());
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use std::{env, fs, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
 
+#[allow(clippy::io_other_error)]
 pub fn create_temp_dir() -> std::io::Result<TempDir> {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -1,3 +1,5 @@
+#![allow(clippy::assertions_on_constants)]
+
 use drgrep::Color;
 use std::io::{self, Write};
 
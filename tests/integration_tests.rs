@@ -1,10 +1,14 @@
 // tests/integration_test.rs
 
 use drgrep::{
-    args::parser::ArgParser, regex::pattern::RegexPattern, search_insensitive_case,
-    search_sensitive_case, Config,
+    args::parser::ArgParser,
+    regex::pattern::{Boundary, RegexPattern},
+    search_fixed_strings, search_insensitive_case, search_sensitive_case, search_with_pattern_set,
+    temp_dir::create_temp_dir,
+    Config, DrgrepError, SPEC,
 };
 use std::collections::HashMap;
+use std::fs;
 
 #[test]
 fn test_search_functionality() {
@@ -32,14 +36,14 @@ fn test_config_creation() {
     args_map.insert("path".to_string(), Some("./src".to_string()));
     args_map.insert("sensitive".to_string(), Some("true".to_string()));
 
-    let args = ArgParser { args: args_map };
+    let args = ArgParser::from(args_map);
 
     // Create config from args
     let config = Config::new(&args).unwrap();
 
     // Test values
     assert_eq!("test", config.search_key.unwrap());
-    assert_eq!(Some("./src"), config.file_path);
+    assert_eq!(vec!["./src"], config.paths);
     assert!(config.sensitive);
 }
 
@@ -74,7 +78,7 @@ fn test_config_with_regex() {
     args_map.insert("key".to_string(), Some("test".to_string()));
     args_map.insert("regex".to_string(), Some("\\w+".to_string()));
 
-    let args = ArgParser { args: args_map };
+    let args = ArgParser::from(args_map);
 
     // Create config from args
     let config = Config::new(&args).unwrap();
@@ -89,21 +93,669 @@ fn test_config_with_regex() {
 #[test]
 fn test_error_handling() {
     // Test missing key argument
-    let args = ArgParser {
-        args: HashMap::new(),
-    };
+    let args = ArgParser::from(HashMap::new());
 
     let result = Config::new(&args);
     assert!(result.is_err());
-    assert_eq!("no search key/regex provided", result.unwrap_err());
+    assert_eq!(
+        "no search key/regex provided",
+        result.unwrap_err().to_string()
+    );
+
+    // Test unknown options and invalid values, checked against the spec
+    let args = ArgParser::from(HashMap::from([
+        ("k".to_string(), Some("test".to_string())),
+        ("sensitve".to_string(), None),
+    ]));
+    let error = Config::new(&args).unwrap_err();
+    assert!(matches!(error, DrgrepError::UnknownOption(_)));
+    assert_eq!(
+        "unknown option --sensitve, did you mean --sensitive?",
+        error.to_string()
+    );
+    // The old permissive behavior, for the scripts passing unknown options
+    let lenient = Config::with_lenient(&args, true);
+    assert_eq!(Some("test"), lenient.unwrap().search_key);
+    let args = ArgParser::from(HashMap::from([
+        ("k".to_string(), Some("test".to_string())),
+        ("fuzzy".to_string(), Some("two".to_string())),
+    ]));
+    assert_eq!(
+        "--fuzzy expects a number, got 'two'",
+        Config::new(&args).unwrap_err().to_string()
+    );
 
     // Test invalid regex pattern
     let mut args_map = HashMap::new();
     args_map.insert("key".to_string(), Some("test".to_string()));
     args_map.insert("regex".to_string(), Some("*invalid".to_string())); // Invalid pattern (starts with quantifier)
 
-    let args = ArgParser { args: args_map };
+    let args = ArgParser::from(args_map);
 
-    let result = Config::new(&args);
-    assert!(result.is_err());
+    // The error explains the pattern
+    match Config::new(&args) {
+        Err(DrgrepError::Pattern(diagnostic)) => {
+            assert_eq!("*invalid", diagnostic.pattern());
+            assert_eq!(Some((0, 1)), diagnostic.span());
+            assert!(diagnostic.hints()[0].contains(r"`\*`"));
+        }
+        other => panic!("expected a pattern error, got {:?}", other.err()),
+    }
+
+    // The pattern of a set failing to compile is pointed out
+    let mut args = ArgParser::from(HashMap::new());
+    args.values.insert(
+        "e".to_string(),
+        vec!["ok".to_string(), "(unclosed".to_string()],
+    );
+    args.args
+        .insert("e".to_string(), Some("(unclosed".to_string()));
+    match Config::new(&args) {
+        Err(DrgrepError::Pattern(diagnostic)) => assert_eq!("(unclosed", diagnostic.pattern()),
+        other => panic!("expected a pattern error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn test_config_with_multiple_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = create_temp_dir()?;
+    let patterns_file = dir.path().join("patterns.txt");
+    std::fs::write(&patterns_file, "warn\n\n\\d{3}\n")?;
+
+    let mut args = ArgParser::from(HashMap::new());
+    args.values.insert(
+        "e".to_string(),
+        vec!["error".to_string(), "fatal".to_string()],
+    );
+    args.args.insert("e".to_string(), Some("fatal".to_string()));
+    args.set("f", patterns_file.to_str().unwrap().to_string());
+
+    let config = Config::new(&args)?;
+    assert!(config.regex.is_none());
+    let set = config.pattern_set.as_ref().unwrap();
+    assert_eq!(4, set.len());
+
+    let content = "\
+fatal error 500
+all good
+warn: disk";
+    let results = search_with_pattern_set(set, "", content);
+    assert_eq!(2, results.len());
+    assert_eq!(1, results[0].idx);
+    assert_eq!(vec!["error", "fatal", r"\d{3}"], results[0].patterns);
+    assert_eq!(3, results[1].idx);
+    assert_eq!(vec!["warn"], results[1].patterns);
+    Ok(())
+}
+
+#[test]
+fn test_config_with_missing_patterns_file() {
+    let mut args = ArgParser::from(HashMap::new());
+    args.set("f", "./does/not/exist.txt".to_string());
+    match Config::new(&args) {
+        Err(DrgrepError::Io { path, .. }) => {
+            assert_eq!("./does/not/exist.txt", path.to_str().unwrap())
+        }
+        other => panic!("expected an I/O error, got {:?}", other.err()),
+    }
+
+    let mut args = ArgParser::from(HashMap::new());
+    args.set("k", "key".to_string());
+    args.args.insert("no-messages".to_string(), None);
+    assert!(Config::new(&args).unwrap().no_messages);
+}
+
+#[test]
+fn test_config_with_fixed_strings() {
+    let mut args_map = HashMap::new();
+    args_map.insert("key".to_string(), Some("a.b".to_string()));
+    args_map.insert("e".to_string(), Some("(x)".to_string()));
+    args_map.insert("F".to_string(), None);
+    let args = ArgParser::from(args_map);
+
+    let config = Config::new(&args).unwrap();
+    assert!(config.regex.is_none());
+    let fixed = config.fixed_strings.as_ref().unwrap();
+    assert_eq!(vec!["a.b", "(x)"], fixed.patterns());
+
+    let content = "\
+axb
+call(x) on A.B
+nothing";
+    let results = search_fixed_strings(fixed, Boundary::None, "", content);
+    assert_eq!(1, results.len());
+    assert_eq!(2, results[0].idx);
+    assert_eq!(vec![(4, 7), (11, 14)], results[0].spans);
+    assert_eq!(vec!["(x)", "a.b"], results[0].patterns);
+    let line: String = results[0].line.iter().map(|(t, _)| *t).collect();
+    assert_eq!("call(x) on A.B", line);
+}
+
+/// Builds the arguments from flags and their optional value
+fn args_for(flags: &[(&str, Option<&str>)]) -> ArgParser {
+    let args_map: HashMap<String, Option<String>> = flags
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.map(|v| v.to_string())))
+        .collect();
+    ArgParser::from(args_map)
+}
+
+fn search_lines(args_map: HashMap<String, Option<String>>, content: &str) -> Vec<usize> {
+    let args = ArgParser::from(args_map);
+    let config = Config::new(&args).unwrap();
+    let mut results = Vec::new();
+    if let Some(fixed) = &config.fixed_strings {
+        results = search_fixed_strings(fixed, config.boundary, "", content);
+    } else if let Some(reg) = &config.regex {
+        results = drgrep::search_with_regex(reg, "", content);
+    }
+    results.iter().map(|r| r.idx).collect()
+}
+
+#[test]
+fn test_word_and_line_regexp() {
+    let content = "\
+width idle valid
+the id, then
+ID
+id";
+    let mut args_map = HashMap::new();
+    args_map.insert("k".to_string(), Some("id".to_string()));
+    args_map.insert("w".to_string(), None);
+    assert_eq!(vec![2, 3, 4], search_lines(args_map.clone(), content));
+
+    args_map.insert("s".to_string(), None);
+    assert_eq!(vec![2, 4], search_lines(args_map.clone(), content));
+
+    args_map.insert("F".to_string(), None);
+    assert_eq!(vec![2, 4], search_lines(args_map.clone(), content));
+
+    args_map.insert("x".to_string(), None);
+    assert_eq!(vec![4], search_lines(args_map.clone(), content));
+
+    let mut args_map = HashMap::new();
+    args_map.insert("r".to_string(), Some("i.".to_string()));
+    args_map.insert("x".to_string(), None);
+    assert_eq!(vec![4], search_lines(args_map, content));
+}
+
+#[test]
+fn test_word_regexp_highlighting() {
+    let mut args_map = HashMap::new();
+    args_map.insert("k".to_string(), Some("id".to_string()));
+    args_map.insert("w".to_string(), None);
+    let args = ArgParser::from(args_map);
+    let config = Config::new(&args).unwrap();
+
+    let results = drgrep::search_with_regex(config.regex.as_ref().unwrap(), "", "valid id, idle");
+    assert_eq!(vec![(6, 8)], results[0].spans);
+    assert_eq!(
+        vec![
+            ("valid ", drgrep::Color::WHITE),
+            ("id", drgrep::Color::BRIGHT_YELLOW),
+            (", idle", drgrep::Color::WHITE)
+        ],
+        results[0].line
+    );
+}
+
+#[test]
+fn test_smart_case() {
+    let args = args_for(&[("k", Some("rust")), ("S", None)]);
+    assert!(!Config::new(&args).unwrap().sensitive);
+
+    let args = args_for(&[("k", Some("Rust")), ("smart-case", None)]);
+    assert!(Config::new(&args).unwrap().sensitive);
+
+    let args = args_for(&[("k", Some("Rust")), ("S", None), ("i", None)]);
+    assert!(!Config::new(&args).unwrap().sensitive);
+
+    // The smart case applies to the regexes too, escapes like \S don't count
+    let args = args_for(&[("r", Some(r"rust\S*")), ("S", None)]);
+    let config = Config::new(&args).unwrap();
+    assert!(config.regex.as_ref().unwrap().is_match("RUSTIQUE"));
+
+    let args = args_for(&[("r", Some(r"Rust\S*")), ("S", None)]);
+    let config = Config::new(&args).unwrap();
+    assert!(!config.regex.as_ref().unwrap().is_match("RUSTIQUE"));
+
+    // Without any flag the regexes stay case-sensitive
+    let args = args_for(&[("r", Some("rust"))]);
+    let config = Config::new(&args).unwrap();
+    assert!(!config.regex.as_ref().unwrap().is_match("RUST"));
+}
+
+#[test]
+fn test_ignore_accents() {
+    // The accents of the patterns are stripped too
+    let args = args_for(&[("r", Some("s[ée]curit[ée]")), ("ignore-accents", None)]);
+    let config = Config::new(&args).unwrap();
+    assert!(config.ignore_accents);
+    assert_eq!(
+        "s[ee]curit[ee]",
+        config.regex.as_ref().unwrap().get_pattern()
+    );
+
+    let args = args_for(&[("F", None), ("e", Some("Élève")), ("ignore-accents", None)]);
+    let config = Config::new(&args).unwrap();
+    assert_eq!(
+        &["Eleve"],
+        config.fixed_strings.as_ref().unwrap().patterns()
+    );
+
+    let args = args_for(&[
+        ("k", Some("café")),
+        ("ignore-accents", None),
+        ("replace", Some("thé")),
+        ("in-place", None),
+    ]);
+    assert!(Config::new(&args).is_err());
+}
+
+#[test]
+fn test_fuzzy_flags() {
+    let args = args_for(&[("k", Some("receive")), ("fuzzy", Some("1"))]);
+    let config = Config::new(&args).unwrap();
+    let fuzzy = config.fuzzy.as_ref().unwrap();
+    assert_eq!(1, fuzzy.max_distance());
+    assert!(fuzzy.is_match("we recive it"));
+    assert!(config.regex.is_none());
+
+    for flags in [
+        &[("k", Some("receive")), ("fuzzy", None)][..],
+        &[("k", Some("receive")), ("fuzzy", Some("many"))],
+        &[("k", Some("ab")), ("fuzzy", Some("2"))],
+        &[("r", Some("receive")), ("fuzzy", Some("1"))],
+        &[("k", Some("receive")), ("sort", Some("distance"))],
+        &[
+            ("k", Some("receive")),
+            ("fuzzy", Some("1")),
+            ("sort", Some("name")),
+        ],
+    ] {
+        assert!(Config::new(&args_for(flags)).is_err());
+    }
+}
+
+#[test]
+fn test_regex_engine() {
+    let doubled = r"\b(\w+)\s+\1\b";
+    assert!(Config::new(&args_for(&[("r", Some(doubled))])).is_err());
+    // Only available with the pcre2 feature
+    let args = args_for(&[("r", Some(doubled)), ("engine", Some("pcre2"))]);
+    let pcre2 = Config::new(&args);
+    assert_eq!(cfg!(feature = "pcre2"), pcre2.is_ok());
+    if let Ok(config) = pcre2 {
+        assert!(config.regex.as_ref().unwrap().is_match("it is is fine"));
+    }
+    assert!(Config::new(&args_for(&[("r", Some(doubled)), ("engine", Some("perl"))])).is_err());
+
+    if cfg!(feature = "fancy") {
+        let args = args_for(&[("r", Some(doubled)), ("engine", Some("auto"))]);
+        let config = Config::new(&args).unwrap();
+        let regex = config.regex.as_ref().unwrap();
+        assert!(regex.is_match("it is is fine"));
+        assert!(!regex.is_match("it is fine"));
+
+        let args = args_for(&[
+            ("r", Some(doubled)),
+            ("e", Some("foo(?!bar)")),
+            ("engine", Some("fancy")),
+        ]);
+        let config = Config::new(&args).unwrap();
+        let set = config.pattern_set.as_ref().unwrap();
+        assert_eq!(vec![1], set.matches("foobaz"));
+
+        // The errors point in the pattern given, not in the wrapped one which is compiled
+        for flag in ["w", "x", "ignore-accents"] {
+            let args = args_for(&[("r", Some("été)")), ("engine", Some("fancy")), (flag, None)]);
+            let error = Config::new(&args).unwrap_err().to_string();
+            assert!(error.contains("\n  été)\n     ^\n"), "{}", error);
+        }
+    }
+}
+
+#[test]
+fn test_regex_size_limit() {
+    let args = args_for(&[("r", Some(r"\w{50}")), ("regex-size-limit", Some("100"))]);
+    let error = Config::new(&args).unwrap_err().to_string();
+    assert!(error.starts_with("error: the compiled regex exceeds the size limit of 100 bytes"));
+    assert!(error.contains("--regex-size-limit"));
+    let args = args_for(&[("r", Some(r"\w{50}")), ("regex-size-limit", Some("lots"))]);
+    assert!(Config::new(&args).is_err());
+
+    // The case options are set on the builder, the patterns are kept as written
+    let args = args_for(&[("r", Some("rust")), ("e", Some("go")), ("i", None)]);
+    let config = Config::new(&args).unwrap();
+    let set = config.pattern_set.as_ref().unwrap();
+    assert_eq!(vec![0, 1], set.matches("RUST and GO"));
+    assert_eq!("rust", set.patterns()[0].get_pattern());
+}
+
+#[test]
+fn test_multiline_flags() {
+    let args = args_for(&[("r", Some(r"^b$")), ("U", None)]);
+    let config = Config::new(&args).unwrap();
+    assert!(config.multiline);
+    assert!(config.regex.as_ref().unwrap().is_match("a\nb\nc"));
+
+    // The key is searched as a regex
+    let args = args_for(&[("k", Some("a.b")), ("multiline", None)]);
+    let config = Config::new(&args).unwrap();
+    assert!(!config.regex.as_ref().unwrap().is_match("axb"));
+
+    for flags in [
+        &[("k", Some("ab")), ("F", None), ("U", None)][..],
+        &[("k", Some("abc")), ("fuzzy", Some("1")), ("U", None)],
+        &[
+            ("r", Some("a")),
+            ("U", None),
+            ("replace", Some("b")),
+            ("in-place", None),
+        ],
+    ] {
+        assert!(Config::new(&args_for(flags)).is_err());
+    }
+}
+
+#[test]
+fn test_only_matching_and_capture() {
+    let content = "\
+[package]
+version = \"0.2.3\"
+[dependencies]
+regex = { version = \"1.11.1\" } # version = \"1.0\"";
+
+    let args = args_for(&[("r", Some(r"\d+")), ("o", None)]);
+    let config = Config::new(&args).unwrap();
+    let values =
+        drgrep::extract::values(&config, config.values.as_ref().unwrap(), content).unwrap();
+    assert_eq!(vec!["0", "2", "3", "1", "11", "1", "1", "0"], values);
+
+    let args = args_for(&[
+        ("r", Some(r#"version = "(?P<v>[^"]+)""#)),
+        ("capture", Some("v")),
+        ("unique", None),
+    ]);
+    let config = Config::new(&args).unwrap();
+    assert!(config.unique);
+    let values =
+        drgrep::extract::values(&config, config.values.as_ref().unwrap(), content).unwrap();
+    assert_eq!(vec!["0.2.3", "1.11.1", "1.0"], values);
+
+    // The key is searched as a regex, the matches keep their case
+    let args = args_for(&[("k", Some("VERSION")), ("only-matching", None)]);
+    let config = Config::new(&args).unwrap();
+    let values =
+        drgrep::extract::values(&config, config.values.as_ref().unwrap(), content).unwrap();
+    assert_eq!(vec!["version"; 3], values);
+
+    // The overlapping matches of the patterns are merged
+    let mut args = args_for(&[("o", None)]);
+    args.values.insert(
+        "e".to_string(),
+        vec!["version".to_string(), r"ver\w+ =".to_string()],
+    );
+    args.args
+        .insert("e".to_string(), Some(r"ver\w+ =".to_string()));
+    let config = Config::new(&args).unwrap();
+    let values =
+        drgrep::extract::values(&config, config.values.as_ref().unwrap(), content).unwrap();
+    assert_eq!(vec!["version ="; 3], values);
+
+    for flags in [
+        &[("r", Some(r"(\d+)")), ("capture", Some("2"))][..],
+        &[("r", Some(r"(\d+)")), ("capture", Some("name"))],
+        &[("k", Some("version")), ("capture", Some("1"))],
+        &[("r", Some(r"\d+")), ("unique", None)],
+        &[("r", Some(r"\d+")), ("count-values", None)],
+    ] {
+        assert!(Config::new(&args_for(flags)).is_err());
+    }
+}
+
+#[test]
+fn test_extract_table() {
+    let content = "\
+2025-04-17T20:40:36 ERROR connection lost, retrying
+not a log line
+2025-04-17T20:41:00 WARN disk";
+    let regex = r"(?P<ts>\S+) (?P<level>[A-Z]+) (?P<msg>.*)";
+
+    let args = args_for(&[("r", Some(regex)), ("extract", Some("csv"))]);
+    let config = Config::new(&args).unwrap();
+    assert_eq!(Some(drgrep::extract::Format::Csv), config.extract);
+    let rows = drgrep::extract::rows(&config, content).unwrap();
+    assert_eq!(2, rows.len());
+    assert_eq!(
+        (
+            1,
+            vec![
+                Some("2025-04-17T20:40:36"),
+                Some("ERROR"),
+                Some("connection lost, retrying")
+            ]
+        ),
+        rows[0]
+    );
+    assert_eq!(3, rows[1].0);
+    let mut table = drgrep::extract::Table::new(
+        drgrep::extract::Format::Csv,
+        &config.regex.as_ref().unwrap().group_names(),
+    );
+    assert_eq!("path,line,ts,level,msg", table.header().unwrap());
+    assert_eq!(
+        "app.log,1,2025-04-17T20:40:36,ERROR,\"connection lost, retrying\"",
+        table.format_row("app.log", &rows[0])
+    );
+
+    // A multiline match is reported at its first line
+    let args = args_for(&[
+        ("r", Some(r"(?P<word>not)\s+a\s+log\s+(?P<rest>line\n\S+)")),
+        ("extract", Some("json")),
+        ("U", None),
+    ]);
+    let config = Config::new(&args).unwrap();
+    let rows = drgrep::extract::rows(&config, content).unwrap();
+    assert_eq!(
+        vec![(2, vec![Some("not"), Some("line\n2025-04-17T20:41:00")])],
+        rows
+    );
+
+    // Even when its groups start on the next lines
+    let args = args_for(&[
+        ("r", Some(r"retrying\n(?P<next>\S+)")),
+        ("extract", Some("csv")),
+        ("U", None),
+    ]);
+    let config = Config::new(&args).unwrap();
+    let rows = drgrep::extract::rows(&config, content).unwrap();
+    assert_eq!(vec![(1, vec![Some("not")])], rows);
+
+    for flags in [
+        &[("r", Some(regex)), ("extract", Some("xml"))][..],
+        &[("r", Some(r"(\d+)")), ("extract", Some("csv"))],
+        &[("k", Some("error")), ("extract", Some("csv"))],
+        &[("r", Some(regex)), ("extract", Some("csv")), ("o", None)],
+    ] {
+        assert!(Config::new(&args_for(flags)).is_err());
+    }
+}
+
+#[test]
+fn test_posix_syntax() {
+    let content = "foofoo bar\nfoo (bar)\nid42";
+
+    let args = args_for(&[("r", Some(r"\(foo\)\{2\}")), ("G", None)]);
+    let config = Config::new(&args).unwrap();
+    let regex = config.regex.as_ref().unwrap();
+    let lines: Vec<usize> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| regex.is_match(l))
+        .map(|(i, _)| i + 1)
+        .collect();
+    assert_eq!(vec![1], lines);
+
+    // The parentheses are literals in BRE
+    let args = args_for(&[("r", Some(r"(bar)")), ("basic-regexp", None)]);
+    let config = Config::new(&args).unwrap();
+    let regex = config.regex.as_ref().unwrap();
+    let lines: Vec<usize> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| regex.is_match(l))
+        .map(|(i, _)| i + 1)
+        .collect();
+    assert_eq!(vec![2], lines);
+
+    let args = args_for(&[
+        ("r", Some(r"^[[:alpha:]]+[[:digit:]]{2}$")),
+        ("extended-regexp", None),
+    ]);
+    let config = Config::new(&args).unwrap();
+    let regex = config.regex.as_ref().unwrap();
+    let lines: Vec<usize> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| regex.is_match(l))
+        .map(|(i, _)| i + 1)
+        .collect();
+    assert_eq!(vec![3], lines);
+
+    for flags in [
+        &[("r", Some(r"[[:alfa:]]")), ("G", None)][..],
+        &[("r", Some(r"a(b")), ("E", None)],
+        &[("r", Some("a")), ("G", None), ("E", None)],
+        &[("r", Some("a")), ("E", None), ("F", None)],
+    ] {
+        assert!(Config::new(&args_for(flags)).is_err());
+    }
+}
+
+#[test]
+fn test_positional_arguments() {
+    let parse = |args: &[&str]| ArgParser::parse(args.iter().copied(), Some(&SPEC));
+
+    // The first positional is the pattern, the others are the paths, in order
+    let args = parse(&["fo+", "src", "Cargo.toml", "-p", "tests"]);
+    let config = Config::new(&args).unwrap();
+    assert!(config.regex.as_ref().unwrap().is_match("foo"));
+    assert_eq!(vec!["src", "Cargo.toml", "tests"], config.paths);
+    let args = parse(&[
+        "-p",
+        "tests",
+        "fo+",
+        "src",
+        "--path",
+        "Cargo.toml",
+        "-p",
+        ".",
+    ]);
+    let config = Config::new(&args).unwrap();
+    assert_eq!(vec!["tests", "src", "Cargo.toml", "."], config.paths);
+
+    // An option giving the pattern makes every positional a path
+    let args = parse(&["-k", "foo", "src", "--", "-r"]);
+    assert_eq!(vec!["src", "-r"], Config::new(&args).unwrap().paths);
+    let args = parse(&["-k", "foo", "src"]);
+    let config = Config::new(&args).unwrap();
+    assert_eq!(Some("foo"), config.search_key);
+    assert!(config.regex.is_none());
+    assert_eq!(vec!["src"], config.paths);
+
+    // The missing paths are reported while searching, the current directory isn't searched
+    let args = parse(&["foo", "does/not/exist", "src"]);
+    assert_eq!(
+        vec!["does/not/exist", "src"],
+        Config::new(&args).unwrap().paths
+    );
+    assert!(Config::new(&parse(&["foo", "src", "-c", "text"])).is_err());
+}
+
+#[test]
+fn test_run_reports_the_failed_sources() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = create_temp_dir()?;
+    let file = dir.path().join("a.txt");
+    fs::write(&file, "foo\n")?;
+    let file = file.to_str().unwrap();
+    let parse = |args: &[&str]| ArgParser::parse(args.iter().copied(), Some(&SPEC));
+
+    let args = parse(&["-k", "foo", file]);
+    drgrep::run(Config::new(&args)?)?;
+
+    // The other paths are searched, the failure is counted even when not printed
+    let args = parse(&["--no-messages", "-k", "foo", "does/not/exist", file]);
+    match drgrep::run(Config::new(&args)?) {
+        Err(DrgrepError::Incomplete { errors }) => assert_eq!(1, errors),
+        other => panic!("expected an incomplete search, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_stdin_path() {
+    let parse = |args: &[&str]| ArgParser::parse(args.iter().copied(), Some(&SPEC));
+
+    // `-` is the standard input, searched in order with the other paths
+    let args = parse(&["foo", "-", "src"]);
+    let config = Config::new(&args).unwrap();
+    assert_eq!(vec!["-", "src"], config.paths);
+    let args = parse(&["-k", "foo", "-p", "-"]);
+    assert_eq!(vec!["-"], Config::new(&args).unwrap().paths);
+    // The deprecated `-c @` reads the standard input too
+    let args = parse(&["-k", "foo", "-c", "@"]);
+    let config = Config::new(&args).unwrap();
+    assert_eq!((vec!["-"], None), (config.paths, config.search_content));
+
+    // The standard input can't be rewritten
+    let args = parse(&["foo", "-", "--replace", "bar", "--in-place"]);
+    assert_eq!(
+        "--in-place/--interactive can't rewrite the standard input",
+        Config::new(&args).unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_generated_help() {
+    use drgrep::args::generate::{generate, Target};
+
+    let help = drgrep::help();
+    let man = generate(&drgrep::PROGRAM, Target::Man);
+    let bash = generate(&drgrep::PROGRAM, Target::Bash);
+    for option in SPEC.options() {
+        assert!(help.contains(&format!("--{}", option.name)));
+        assert!(man.contains(&format!(
+            "\\fB\\-\\-{}\\fR",
+            option.name.replace('-', "\\-")
+        )));
+        assert!(bash.contains(&format!("--{}", option.name)));
+    }
+    assert!(help.contains("[possible values: csv, tsv, json]"));
+    assert!(help.contains("[env: DRGREP_SMART_CASE]"));
+    // The file types are completed
+    let zsh = generate(&drgrep::PROGRAM, Target::Zsh);
+    assert!(zsh.contains(":TYPE:(c cpp css go"));
+}
+
+#[test]
+fn test_file_types() {
+    let parse = |args: &[&str]| ArgParser::parse(args.iter().copied(), Some(&SPEC));
+
+    let args = parse(&["foo", "-t", "rust", "--type", "toml"]);
+    let config = Config::new(&args).unwrap();
+    assert_eq!(vec!["rust", "toml"], config.types);
+    assert!(config.has_file_type(std::path::Path::new("src/lib.rs")));
+    assert!(config.has_file_type(std::path::Path::new("Cargo.toml")));
+    assert!(!config.has_file_type(std::path::Path::new("README.md")));
+
+    // Every file without --type
+    let args = parse(&["foo"]);
+    let config = Config::new(&args).unwrap();
+    assert!(config.has_file_type(std::path::Path::new("Makefile")));
+
+    assert!(Config::new(&parse(&["foo", "--type", "cobol"]))
+        .unwrap_err()
+        .to_string()
+        .starts_with("--type expects one of c, cpp,"));
 }
@@ -1,3 +1,5 @@
+#![allow(clippy::assertions_on_constants, clippy::print_literal)]
+
 use drgrep::regex::pattern::{self, PatternError, RegexPattern};
 
 #[test]
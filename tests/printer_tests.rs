@@ -1,4 +1,5 @@
 // In tests/printer_tests.rs
+#![allow(clippy::assertions_on_constants)]
 
 use drgrep::color::config::Color;
 use drgrep::color::printer::{print_colored, print_partial_colored, print_styled};
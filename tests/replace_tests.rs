@@ -0,0 +1,141 @@
+use drgrep::{
+    args::parser::ArgParser,
+    replace::{self, journal},
+    run,
+    temp_dir::create_temp_dir,
+    Config, DrgrepError,
+};
+use std::collections::HashMap;
+use std::fs;
+
+fn replace_args(path: &str, write: bool) -> ArgParser {
+    let mut args = HashMap::new();
+    args.insert("regex".to_string(), Some(r"foo(\d+)".to_string()));
+    args.insert("replace".to_string(), Some("bar$1".to_string()));
+    args.insert("in-place".to_string(), None);
+    args.insert("path".to_string(), Some(path.to_string()));
+    if write {
+        args.insert("write".to_string(), None);
+    }
+    ArgParser::from(args)
+}
+
+#[test]
+fn test_in_place_dry_run_keeps_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = create_temp_dir()?;
+    let file = dir.path().join("a.txt");
+    fs::write(&file, "foo1\nfoo2\n")?;
+
+    let args = replace_args(dir.path().to_str().unwrap(), false);
+    run(Config::new(&args)?)?;
+
+    assert_eq!("foo1\nfoo2\n", fs::read_to_string(&file)?);
+    Ok(())
+}
+
+#[test]
+fn test_in_place_write() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = create_temp_dir()?;
+    fs::create_dir_all(dir.path().join("nested"))?;
+    let file = dir.path().join("nested/a.txt");
+    let untouched = dir.path().join("b.txt");
+    fs::write(&file, "foo1\r\nkeep\r\nfoo22")?;
+    fs::write(&untouched, "nothing here\n")?;
+    let journal_dir = create_temp_dir()?;
+
+    let args = replace_args(dir.path().to_str().unwrap(), true);
+    replace::run_with_journal(&Config::new(&args)?, journal_dir.path())?;
+
+    assert_eq!("bar1\r\nkeep\r\nbar22", fs::read_to_string(&file)?);
+    assert_eq!("nothing here\n", fs::read_to_string(&untouched)?);
+
+    // The run is saved in the journal and can be reverted
+    let runs = journal::list_runs(journal_dir.path())?;
+    assert_eq!(1, runs.len());
+    assert_eq!(1, runs[0].entries.len());
+    journal::undo(journal_dir.path(), Some(&runs[0].id))?;
+    assert_eq!("foo1\r\nkeep\r\nfoo22", fs::read_to_string(&file)?);
+    Ok(())
+}
+
+#[test]
+fn test_in_place_flags_validation() {
+    let mut args = HashMap::new();
+    args.insert("regex".to_string(), Some("foo".to_string()));
+    args.insert("in-place".to_string(), None);
+    assert!(Config::new(&ArgParser::from(args.clone())).is_err());
+
+    args.remove("in-place");
+    args.insert("write".to_string(), None);
+    assert!(Config::new(&ArgParser::from(args.clone())).is_err());
+
+    // --write only applies to --in-place, --interactive writes on its own
+    args.insert("replace".to_string(), Some("bar".to_string()));
+    args.insert("interactive".to_string(), None);
+    assert_eq!(
+        "--write must be used with --in-place",
+        Config::new(&ArgParser::from(args)).unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_in_place_fixed_strings_keep_the_boundary() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = create_temp_dir()?;
+    let file = dir.path().join("a.txt");
+    fs::write(&file, "id width\n")?;
+    let journal_dir = create_temp_dir()?;
+
+    // Like the search, -F -w only replaces the whole words
+    let path = dir.path().to_str().unwrap();
+    let args = ArgParser::parse(
+        [
+            "-F",
+            "-w",
+            "-k",
+            "id",
+            "--replace",
+            "X",
+            "--in-place",
+            "--write",
+            "-p",
+            path,
+        ],
+        Some(&drgrep::SPEC),
+    );
+    replace::run_with_journal(&Config::new(&args)?, journal_dir.path())?;
+
+    assert_eq!("X width\n", fs::read_to_string(&file)?);
+    Ok(())
+}
+
+#[test]
+fn test_in_place_reports_the_failed_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = create_temp_dir()?;
+    let file = dir.path().join("a.txt");
+    fs::write(&file, "foo1\n")?;
+    let journal_dir = create_temp_dir()?;
+
+    let path = file.to_str().unwrap();
+    let args = ArgParser::parse(
+        [
+            "-r",
+            r"foo(\d+)",
+            "--replace",
+            "bar$1",
+            "--in-place",
+            "--write",
+            "--no-messages",
+            "-p",
+            "does/not/exist",
+            "-p",
+            path,
+        ],
+        Some(&drgrep::SPEC),
+    );
+    match replace::run_with_journal(&Config::new(&args)?, journal_dir.path()) {
+        Err(DrgrepError::Incomplete { errors }) => assert_eq!(1, errors),
+        other => panic!("expected an incomplete run, got {:?}", other),
+    }
+    assert_eq!("bar1\n", fs::read_to_string(&file)?);
+    Ok(())
+}